@@ -0,0 +1,265 @@
+// 🎯 A tiny block explorer
+// The latest blocks arrive live over the WebSocket feed; everything you click
+// on is fetched from the REST API. The part after `#` in the URL says what is
+// shown (#/block/3, #/tx/<hash>, #/address/<address>), so the back button works.
+
+const LATEST_BLOCKS = 20;
+const WS_PORT = document.querySelector('meta[name="ws-port"]').content;
+
+const view = document.getElementById("view");
+const statusLine = document.getElementById("status");
+const live = document.getElementById("live");
+
+// Build an element; children may be strings (always shown as text, never as HTML)
+function el(tag, attrs = {}, ...children) {
+    const node = document.createElement(tag);
+    for (const [name, value] of Object.entries(attrs)) {
+        node.setAttribute(name, value);
+    }
+    node.append(...children.map((child) => (child instanceof Node ? child : String(child))));
+    return node;
+}
+
+function link(hash, text, className = "") {
+    return el("a", { href: hash, class: className }, text);
+}
+
+function short(hash) {
+    return hash.length > 16 ? `${hash.slice(0, 8)}…${hash.slice(-8)}` : hash;
+}
+
+function time(timestamp) {
+    return new Date(timestamp * 1000).toLocaleString();
+}
+
+const blockLink = (index) => link(`#/block/${index}`, `#${index}`);
+const txLink = (hash) => link(`#/tx/${hash}`, short(hash), "hash");
+const addressLink = (address) =>
+    address === "coinbase" ? "coinbase" : link(`#/address/${encodeURIComponent(address)}`, short(address), "hash");
+
+async function api(path) {
+    const response = await fetch(path);
+    const body = await response.json().catch(() => ({}));
+    if (!response.ok) {
+        throw new Error(body.error || `${path} answered ${response.status}`);
+    }
+    return body;
+}
+
+function table(headers, rows) {
+    return el(
+        "table",
+        {},
+        el("thead", {}, el("tr", {}, ...headers.map((header) => el("th", {}, header)))),
+        el("tbody", {}, ...rows),
+    );
+}
+
+function row(cells, className = "") {
+    return el(
+        "tr",
+        { class: className },
+        ...cells.map((cell) =>
+            typeof cell === "number" ? el("td", { class: "number" }, cell) : el("td", {}, cell),
+        ),
+    );
+}
+
+function details(pairs) {
+    return el("dl", {}, ...pairs.flatMap(([name, value]) => [el("dt", {}, name), el("dd", {}, value)]));
+}
+
+// ---- Views ----
+
+let latestBlocks = null;
+
+function blockRow(block, className = "") {
+    return row(
+        [
+            blockLink(block.index),
+            link(`#/block/${block.index}`, short(block.hash), "hash"),
+            time(block.timestamp),
+            block.data.transaction_table.length,
+            block.difficulty_bits,
+        ],
+        className,
+    );
+}
+
+async function showHome() {
+    const page = await api(`/api/blocks?order=desc&limit=${LATEST_BLOCKS}`);
+    latestBlocks = el("tbody", {}, ...page.items.map((block) => blockRow(block)));
+    view.replaceChildren(
+        el("h2", {}, "Latest blocks"),
+        el(
+            "table",
+            {},
+            el("thead", {}, el("tr", {}, ...["Block", "Hash", "Mined", "Transactions", "Difficulty"].map((h) => el("th", {}, h)))),
+            latestBlocks,
+        ),
+    );
+}
+
+async function showBlock(index) {
+    const [block, transactions] = await Promise.all([
+        api(`/api/blocks/${index}`),
+        api(`/api/blocks/${index}/transactions`),
+    ]);
+    view.replaceChildren(
+        el("h2", {}, `Block #${block.index}`),
+        details([
+            ["Hash", el("span", { class: "hash" }, block.hash)],
+            ["Previous", block.index > 0 ? link(`#/block/${block.index - 1}`, block.prev_hash, "hash") : "—"],
+            ["Next", link(`#/block/${block.index + 1}`, `#${block.index + 1}`)],
+            ["Mined", time(block.timestamp)],
+            ["Nonce", block.nonce],
+            ["Difficulty", `${block.difficulty_bits} bits`],
+        ]),
+        el("h3", {}, "Transactions"),
+        table(
+            ["Hash", "From", "To", "Amount", "Fee"],
+            transactions.map((tx) => row([txLink(tx.tx_hash), addressLink(tx.from), addressLink(tx.to), tx.amount, tx.fee])),
+        ),
+    );
+}
+
+async function showTransaction(hash) {
+    const found = await api(`/api/transactions/${hash}`);
+    const tx = found.transaction;
+    const mined = found.status === "confirmed"
+        ? [
+            ["Block", blockLink(found.block_index)],
+            ["Position", found.position],
+            ["Confirmations", found.confirmations],
+            ["Mined", time(found.timestamp)],
+        ]
+        : [];
+    view.replaceChildren(
+        el("h2", {}, "Transaction"),
+        details([
+            ["Hash", el("span", { class: "hash" }, found.tx_hash)],
+            ["Status", found.status],
+            ...mined,
+            ["From", addressLink(tx.from)],
+            ["To", addressLink(tx.to)],
+            ["Amount", tx.amount],
+            ["Fee", tx.fee],
+            ["Nonce", tx.nonce],
+            ["Signed", tx.signature ? "yes" : "no"],
+        ]),
+    );
+}
+
+async function showAddress(address) {
+    const [balance, history] = await Promise.all([
+        api(`/api/balances/${encodeURIComponent(address)}`),
+        api(`/api/addresses/${encodeURIComponent(address)}/history?order=desc&limit=100`),
+    ]);
+    view.replaceChildren(
+        el("h2", {}, "Address"),
+        details([
+            ["Address", el("span", { class: "hash" }, address)],
+            ["Balance", balance.balance],
+            ["Transactions", history.total_items],
+        ]),
+        el("h3", {}, history.total_items > history.items.length ? `Latest ${history.items.length}` : "History"),
+        table(
+            ["Block", "Transaction", "Direction", "Counterparty", "Amount", "Fee", "Balance after"],
+            history.items.map((entry) =>
+                row([
+                    blockLink(entry.block_index),
+                    txLink(entry.tx_hash),
+                    entry.direction,
+                    addressLink(entry.counterparty),
+                    entry.amount,
+                    entry.fee,
+                    entry.balance,
+                ]),
+            ),
+        ),
+    );
+}
+
+async function route() {
+    const [, kind, id] = location.hash.split("/");
+    latestBlocks = null;
+    try {
+        if (kind === "block") {
+            await showBlock(Number(id));
+        } else if (kind === "tx") {
+            await showTransaction(id);
+        } else if (kind === "address") {
+            await showAddress(decodeURIComponent(id));
+        } else {
+            await showHome();
+        }
+    } catch (error) {
+        view.replaceChildren(el("p", { class: "error" }, error.message));
+    }
+}
+
+// ---- Search ----
+
+document.getElementById("search").addEventListener("submit", (event) => {
+    event.preventDefault();
+    const query = event.target.query.value.trim();
+    if (/^\d+$/.test(query)) {
+        location.hash = `#/block/${query}`;
+    } else if (/^[0-9a-f]{64}$/i.test(query)) {
+        location.hash = `#/tx/${query.toLowerCase()}`;
+    } else if (query) {
+        location.hash = `#/address/${encodeURIComponent(query)}`;
+    }
+});
+
+// ---- Live feed ----
+
+function showStatus(status) {
+    statusLine.textContent = `${status.total_blocks} blocks · ${status.connected_clients} watching`;
+}
+
+async function addMinedBlock(index) {
+    if (!latestBlocks) {
+        return;
+    }
+    try {
+        const block = await api(`/api/blocks/${index}`);
+        latestBlocks.prepend(blockRow(block, "new"));
+        while (latestBlocks.children.length > LATEST_BLOCKS) {
+            latestBlocks.lastChild.remove();
+        }
+    } catch (error) {
+        console.error(error);
+    }
+}
+
+function connect() {
+    const socket = new WebSocket(`ws://${location.hostname}:${WS_PORT}`);
+    socket.onopen = () => {
+        live.className = "online";
+        live.textContent = "● live";
+        socket.send(JSON.stringify({ subscribe: ["BlockMined", "BlockchainUpdated"] }));
+        socket.send(JSON.stringify({ get: "status" }));
+    };
+    socket.onmessage = (message) => {
+        const data = JSON.parse(message.data);
+        if (data.status) {
+            showStatus(data.status);
+        } else if (data.BlockMined) {
+            addMinedBlock(data.BlockMined.block_index);
+        } else if (data.BlockchainUpdated) {
+            socket.send(JSON.stringify({ get: "status" }));
+        }
+    };
+    // Try again in a moment, e.g. while the server restarts
+    socket.onclose = () => {
+        live.className = "offline";
+        live.textContent = "● offline";
+        setTimeout(connect, 3000);
+    };
+}
+
+window.addEventListener("hashchange", route);
+api("/api/status").then(showStatus).catch(() => {});
+route();
+connect();
@@ -0,0 +1,150 @@
+use crate::BlockchainError;
+use ed25519_dalek::VerifyingKey;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::str::FromStr;
+
+// 🎯 What is an Address?
+// The first 20 bytes of SHA-256(public key): short enough to share, and only the
+// owner of the matching secret key can sign for it. Written down, it is
+// base58check encoded like a Bitcoin address:
+//
+//   base58( version byte + 20 byte hash + checksum )
+//
+// The checksum is the first 4 bytes of SHA-256(SHA-256(version + hash)), so a
+// mistyped address is caught by `Address::parse` instead of silently sending
+// coins to nobody. Base58 leaves out 0, O, I and l, which are easy to mix up.
+
+// Every address starts with the same character because of this byte
+const VERSION: u8 = 0x35;
+
+// The "sender" of the coins a block mints. No public key hashes to all zeros, so
+// nobody can sign for it; it is written as plain `coinbase`.
+pub const COINBASE: Address = Address([0; 20]);
+const COINBASE_NAME: &str = "coinbase";
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Address([u8; 20]);
+
+impl Address {
+    pub fn from_public_key(public_key: &VerifyingKey) -> Address {
+        let digest = Sha256::digest(public_key.as_bytes());
+        let mut hash = [0u8; 20];
+        hash.copy_from_slice(&digest[..20]);
+        Address(hash)
+    }
+
+    // Read an address back from its base58check form, checking the version and checksum
+    pub fn parse(text: &str) -> Result<Address, BlockchainError> {
+        if text == COINBASE_NAME {
+            return Ok(COINBASE);
+        }
+        let invalid = |reason: String| BlockchainError::InvalidAddress {
+            address: text.to_string(),
+            reason,
+        };
+
+        let decoded = bs58::decode(text)
+            .with_check(Some(VERSION))
+            .into_vec()
+            .map_err(|e| {
+                invalid(match e {
+                    bs58::decode::Error::InvalidChecksum { .. } => {
+                        "the checksum doesn't match, is there a typo?".to_string()
+                    }
+                    bs58::decode::Error::InvalidVersion { .. } => {
+                        "it has the wrong version byte".to_string()
+                    }
+                    e => e.to_string(),
+                })
+            })?;
+        // The decoded bytes still start with the version byte
+        let hash = <[u8; 20]>::try_from(&decoded[1..])
+            .map_err(|_| invalid(format!("{} bytes instead of 20", decoded.len() - 1)))?;
+        Ok(Address(hash))
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if *self == COINBASE {
+            return f.write_str(COINBASE_NAME);
+        }
+        let encoded = bs58::encode(self.0)
+            .with_check_version(VERSION)
+            .into_string();
+        f.write_str(&encoded)
+    }
+}
+
+// Shown like it is written, so test failures and logs are readable
+impl fmt::Debug for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Address({})", self)
+    }
+}
+
+impl FromStr for Address {
+    type Err = BlockchainError;
+
+    fn from_str(text: &str) -> Result<Address, BlockchainError> {
+        Address::parse(text)
+    }
+}
+
+impl TryFrom<String> for Address {
+    type Error = BlockchainError;
+
+    fn try_from(text: String) -> Result<Address, BlockchainError> {
+        Address::parse(&text)
+    }
+}
+
+impl From<Address> for String {
+    fn from(address: Address) -> String {
+        address.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wallet::Wallet;
+
+    #[test]
+    fn addresses_round_trip_and_typos_fail_the_checksum() {
+        let address = Wallet::from_name("alice").address();
+        let text = address.to_string();
+        assert_eq!(Address::parse(&text).unwrap(), address);
+        assert_eq!(
+            serde_json::to_string(&address).unwrap(),
+            format!("\"{}\"", text)
+        );
+        // Same version byte, so every address starts the same way
+        assert_eq!(
+            text.chars().next(),
+            Wallet::generate().address().to_string().chars().next()
+        );
+
+        // Swap one character for another valid base58 one
+        let last = text.chars().last().unwrap();
+        let typo = format!(
+            "{}{}",
+            &text[..text.len() - 1],
+            if last == 'z' { 'y' } else { 'z' }
+        );
+        let err = Address::parse(&typo).unwrap_err();
+        assert!(matches!(err, BlockchainError::InvalidAddress { .. }));
+        assert!(err.to_string().contains("checksum"));
+
+        for bad in ["", "alice", "0OIl", &hex::encode([1u8; 20])] {
+            assert!(Address::parse(bad).is_err(), "{} parsed", bad);
+        }
+        assert!(serde_json::from_str::<Address>("\"alice\"").is_err());
+
+        assert_eq!(COINBASE.to_string(), "coinbase");
+        assert_eq!(Address::parse("coinbase").unwrap(), COINBASE);
+    }
+}
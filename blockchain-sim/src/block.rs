@@ -0,0 +1,147 @@
+use crate::difficulty::DEFAULT_DIFFICULTY_BITS;
+use crate::events::EventBus;
+use crate::miner::{Miner, MiningStats};
+use crate::{BlockchainError, HashAlgo, MultipleTransactions};
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn default_difficulty_bits() -> u32 {
+    DEFAULT_DIFFICULTY_BITS
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Block {
+    pub index: u32,
+    pub prev_hash: String,
+    pub timestamp: u64,
+    pub data: MultipleTransactions,
+    pub nonce: u64,
+    pub hash: String,
+    // Leading zero bits the hash needs, set by `BlockChain::prepare_block`
+    #[serde(default = "default_difficulty_bits")]
+    pub difficulty_bits: u32,
+}
+
+// Render a unix timestamp as a UTC date, without silently falling back to 1970
+// when the value is outside what chrono can represent.
+pub fn format_timestamp(timestamp: u64) -> String {
+    i64::try_from(timestamp)
+        .ok()
+        .and_then(|secs| DateTime::from_timestamp(secs, 0))
+        .map(|datetime| datetime.naive_utc().to_string())
+        .unwrap_or_else(|| "invalid timestamp".to_string())
+}
+
+impl fmt::Display for Block {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Block {}: {} at {}",
+            self.index,
+            self.data,
+            format_timestamp(self.timestamp)
+        )
+    }
+}
+
+impl Block {
+    pub fn new(
+        index: u32,
+        prev_hash: String,
+        data: MultipleTransactions,
+    ) -> Result<Block, BlockchainError> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| BlockchainError::TimeError(format!("Time Error : {}", e)))?;
+
+        Ok(Block {
+            index,
+            prev_hash,
+            timestamp: timestamp.as_secs(),
+            data,
+            nonce: 0,
+            hash: String::new(),
+            difficulty_bits: DEFAULT_DIFFICULTY_BITS,
+        })
+    }
+
+    pub fn calculate_hash(&self, algo: HashAlgo) -> String {
+        let data = format!(
+            "{} {} {} {} {}",
+            self.index, &self.prev_hash, self.timestamp, &self.data, self.nonce
+        );
+        algo.digest(data.as_bytes())
+    }
+
+    // Everything `calculate_hash` feeds in before the nonce; it does not change while mining
+    pub(crate) fn hash_prefix(&self) -> String {
+        format!(
+            "{} {} {} {} ",
+            self.index, &self.prev_hash, self.timestamp, &self.data
+        )
+    }
+
+    // 🎯 Mine this block on every core and broadcast the events
+    pub fn mine_block_with_visual_hash(
+        &mut self,
+        algo: HashAlgo,
+        event_bus: &EventBus,
+        miner: &str,
+    ) -> MiningStats {
+        Miner::default().mine(self, algo, event_bus, miner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::sample_block;
+
+    #[test]
+    fn each_hash_algo_is_deterministic_and_distinct() {
+        let block = sample_block();
+        let algos = [HashAlgo::Sha256, HashAlgo::Sha3_256, HashAlgo::Blake3];
+        let hashes: Vec<String> = algos.iter().map(|a| block.calculate_hash(*a)).collect();
+
+        for (algo, hash) in algos.iter().zip(&hashes) {
+            assert_eq!(hash, &block.calculate_hash(*algo));
+            assert_eq!(hash.len(), 64);
+        }
+        assert_ne!(hashes[0], hashes[1]);
+        assert_ne!(hashes[0], hashes[2]);
+        assert_ne!(hashes[1], hashes[2]);
+    }
+
+    #[test]
+    fn prefix_hasher_matches_calculate_hash() {
+        let mut block = sample_block();
+        for algo in [HashAlgo::Sha256, HashAlgo::Sha3_256, HashAlgo::Blake3] {
+            let prefix_hasher = algo.prefix_hasher(block.hash_prefix().as_bytes());
+            for nonce in [0, 1, 9, 10, 12_345, u64::MAX] {
+                block.nonce = nonce;
+                assert_eq!(
+                    prefix_hasher.hash_with_nonce(nonce),
+                    block.calculate_hash(algo)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn formats_normal_timestamp() {
+        assert_eq!(format_timestamp(1_700_000_000), "2023-11-14 22:13:20");
+        assert!(
+            sample_block()
+                .to_string()
+                .ends_with("at 2023-11-14 22:13:20")
+        );
+    }
+
+    #[test]
+    fn formats_out_of_range_timestamp_as_invalid() {
+        assert_eq!(format_timestamp(u64::MAX), "invalid timestamp");
+        assert_eq!(format_timestamp(i64::MAX as u64), "invalid timestamp");
+    }
+}
@@ -0,0 +1,1323 @@
+use crate::difficulty::{DifficultyConfig, meets_difficulty};
+use crate::events::{BlockchainEvent, EventBus};
+use crate::reward::RewardSchedule;
+use crate::storage::ChainStore;
+use crate::{
+    Address, Block, BlockchainError, Direction, HashAlgo, HistoryEntry, Ledger, Miner,
+    MultipleTransactions, Transaction, TransactionReceipt, wallet,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::Write;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+pub const MAX_TX_PER_BLOCK: usize = 100;
+
+// Every new chain's genesis block carries this timestamp instead of the time it
+// was created, so nodes started with the same settings share a genesis block
+// and can sync with each other. (2023-11-14 22:13:20 UTC)
+const GENESIS_TIMESTAMP: u64 = 1_700_000_000;
+
+fn default_max_tx_per_block() -> usize {
+    MAX_TX_PER_BLOCK
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BlockChain {
+    pub chain: Vec<Block>,
+    // Defaults keep chains saved before these settings existed loadable
+    #[serde(default)]
+    pub hash_algo: HashAlgo,
+    #[serde(default = "default_max_tx_per_block")]
+    pub max_tx_per_block: usize,
+    #[serde(default = "DifficultyConfig::legacy")]
+    pub difficulty: DifficultyConfig,
+    #[serde(default = "RewardSchedule::legacy")]
+    pub rewards: RewardSchedule,
+    // Transaction hash -> index of the block that includes it
+    #[serde(skip)]
+    tx_index: HashMap<String, u32>,
+    // Balances and nonces after the last block, kept up to date as blocks are
+    // appended so checking a new one doesn't replay the whole chain
+    #[serde(skip)]
+    ledger: Ledger,
+    // When set, every appended block is written to disk as well
+    #[serde(skip)]
+    store: Option<ChainStore>,
+    // How this node mines; not part of the chain, so it isn't saved with it
+    #[serde(skip)]
+    pub miner: Miner,
+    // Raised on shutdown to abandon the block being mined
+    #[serde(skip)]
+    mining_cancelled: Arc<AtomicBool>,
+}
+
+// First characters of a hash, enough to tell blocks apart in a listing
+fn short_hash(hash: &str) -> String {
+    match hash.get(..12) {
+        Some(prefix) if hash.len() > 12 => format!("{}…", prefix),
+        _ if hash.is_empty() => "(none)".to_string(),
+        _ => hash.to_string(),
+    }
+}
+
+// 🎯 The ledger view: one line per block with its transactions and the
+// running totals of everything moved (and paid in fees) up to that block.
+impl fmt::Display for BlockChain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Ledger: {} blocks, hashed with {:?}",
+            self.chain.len(),
+            self.hash_algo
+        )?;
+
+        let (mut total_amount, mut total_fees) = (0u64, 0u64);
+        for block in &self.chain {
+            let transactions = &block.data.transaction_table;
+            let amount = transactions
+                .iter()
+                .fold(0u64, |sum, tx| sum.saturating_add(tx.amount));
+            let fees = transactions
+                .iter()
+                .fold(0u64, |sum, tx| sum.saturating_add(tx.fee));
+            total_amount = total_amount.saturating_add(amount);
+            total_fees = total_fees.saturating_add(fees);
+
+            writeln!(
+                f,
+                "#{:<4} {:<13} {:>3} tx  amount {:>8}  fees {:>6}  | total {:>10}  fees {:>8}",
+                block.index,
+                short_hash(&block.hash),
+                transactions.len(),
+                amount,
+                fees,
+                total_amount,
+                total_fees
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl BlockChain {
+    pub fn new() -> Result<BlockChain, BlockchainError> {
+        Self::with_hash_algo(HashAlgo::default())
+    }
+
+    pub fn with_hash_algo(hash_algo: HashAlgo) -> Result<BlockChain, BlockchainError> {
+        let genesis_block_data = MultipleTransactions {
+            transaction_table: vec![],
+        };
+        let mut genesis_block = Block::new(0, String::new(), genesis_block_data)?;
+        genesis_block.timestamp = GENESIS_TIMESTAMP;
+        // The genesis block isn't mined, but it still gets a real hash so it can be
+        // validated like every other block and the first mined block links to it.
+        genesis_block.hash = genesis_block.calculate_hash(hash_algo);
+        Ok(BlockChain {
+            chain: vec![genesis_block],
+            hash_algo,
+            max_tx_per_block: MAX_TX_PER_BLOCK,
+            difficulty: DifficultyConfig::default(),
+            rewards: RewardSchedule::default(),
+            tx_index: HashMap::new(),
+            ledger: Ledger::new(),
+            store: None,
+            miner: Miner::default(),
+            mining_cancelled: Arc::default(),
+        })
+    }
+
+    // Mine with this many threads instead of one per CPU core
+    pub fn with_miner(mut self, miner: Miner) -> BlockChain {
+        self.miner = miner;
+        self
+    }
+
+    // Stop the block that is being mined from finishing, and every later one
+    // from starting. Its transactions go back to the mempool.
+    pub fn cancel_mining(&self) {
+        self.mining_cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn with_max_tx_per_block(mut self, max_tx_per_block: usize) -> BlockChain {
+        self.max_tx_per_block = max_tx_per_block;
+        self
+    }
+
+    // Set how hard mining starts out and how it adjusts. Like the genesis
+    // allocations, this belongs to a fresh chain.
+    pub fn with_difficulty(mut self, difficulty: DifficultyConfig) -> BlockChain {
+        self.difficulty = difficulty;
+        self.chain[0].difficulty_bits = difficulty.initial_bits;
+        self
+    }
+
+    // Set the block reward and how often it halves, on a fresh chain
+    pub fn with_rewards(mut self, rewards: RewardSchedule) -> BlockChain {
+        self.rewards = rewards;
+        self
+    }
+
+    // Hand out starting balances in the genesis block (a "premine"), so there are
+    // coins to spend before anyone has mined. Only call this on a fresh chain:
+    // the genesis hash changes and later blocks would no longer link to it.
+    pub fn with_genesis_allocations(mut self, allocations: &[(Address, u64)]) -> BlockChain {
+        let genesis = &mut self.chain[0];
+        genesis.data.transaction_table = allocations
+            .iter()
+            .map(|(address, amount)| Transaction::coinbase(address, *amount, 0))
+            .collect();
+        genesis.hash = genesis.calculate_hash(self.hash_algo);
+        self.reindex();
+        self
+    }
+
+    // Write the chain so far to `store` and keep appending to it as blocks are
+    // mined. Use it on a new chain; a stored one comes back with `ChainStore::load`.
+    pub fn with_store(self, store: ChainStore) -> Result<BlockChain, BlockchainError> {
+        store.save_settings(&self)?;
+        for block in &self.chain {
+            store.append(block)?;
+        }
+        Ok(self.with_existing_store(store))
+    }
+
+    // Attach a store that already holds exactly these blocks
+    pub(crate) fn with_existing_store(mut self, store: ChainStore) -> BlockChain {
+        self.store = Some(store);
+        self
+    }
+
+    // Make sure every block is on disk, e.g. before the program exits.
+    // Without a store there is nothing to do.
+    pub fn flush(&self) -> Result<(), BlockchainError> {
+        match &self.store {
+            Some(store) => store.flush(),
+            None => Ok(()),
+        }
+    }
+
+    // Put a chain back together from blocks read back from storage
+    pub(crate) fn from_blocks(
+        chain: Vec<Block>,
+        hash_algo: HashAlgo,
+        max_tx_per_block: usize,
+        difficulty: DifficultyConfig,
+        rewards: RewardSchedule,
+    ) -> BlockChain {
+        let mut chain = BlockChain {
+            chain,
+            hash_algo,
+            max_tx_per_block,
+            difficulty,
+            rewards,
+            tx_index: HashMap::new(),
+            ledger: Ledger::new(),
+            store: None,
+            miner: Miner::default(),
+            mining_cancelled: Arc::default(),
+        };
+        chain.reindex();
+        chain
+    }
+
+    // Balance and nonce of every address as of the last block
+    pub fn ledger(&self) -> &Ledger {
+        &self.ledger
+    }
+
+    // 🎯 Updated to broadcast events when adding blocks
+    // Validate a candidate block and point it at the current tip so it can be
+    // mined without holding any lock on the chain.
+    pub fn prepare_block(&self, mut new_block: Block) -> Result<Block, BlockchainError> {
+        // Reject oversized blocks before spending any work mining them
+        self.check_tx_count(&new_block)?;
+
+        // Amount + fee has to fit in a u64
+        for transaction in &new_block.data.transaction_table {
+            transaction.total_cost()?;
+        }
+
+        // Every transfer must be signed by its sender
+        check_signatures(&new_block)?;
+
+        new_block.prev_hash = self.chain.last().unwrap().hash.clone();
+        new_block.difficulty_bits = self.next_difficulty();
+        Ok(new_block)
+    }
+
+    // 🎯 Append an already mined block and broadcast the update.
+    // Only this step needs the write lock, so it stays short.
+    pub fn add_new_block(
+        &mut self,
+        new_block: Block,
+        event_bus: &EventBus,
+    ) -> Result<(), BlockchainError> {
+        let changes = self.check_next_block(&new_block)?;
+        let (index, previous_bits) = (new_block.index, new_block.difficulty_bits);
+
+        // On disk first: if that fails the block isn't added at all
+        if let Some(store) = &self.store {
+            store.append(&new_block)?;
+        }
+
+        self.push_block(new_block, changes);
+
+        // 🎯 Broadcast that blockchain was updated
+        event_bus.broadcast(BlockchainEvent::BlockchainUpdated {
+            total_blocks: self.chain.len(),
+            total_transactions: self
+                .chain
+                .iter()
+                .map(|b| b.data.transaction_table.len())
+                .sum(),
+        });
+
+        // 🎯 Every `retarget_interval` blocks the difficulty may move by a bit
+        let difficulty_bits = self.next_difficulty();
+        if difficulty_bits != previous_bits {
+            event_bus.broadcast(BlockchainEvent::DifficultyAdjusted {
+                block_index: index + 1,
+                previous_bits,
+                difficulty_bits,
+            });
+        }
+
+        Ok(())
+    }
+
+    // Everything a block needs to go on top of the current tip. Blocks from peers
+    // and imports come through here too, so nothing about them is taken on trust.
+    // Returns what the block changes in the ledger, for `push_block`.
+    pub(crate) fn check_next_block(&self, new_block: &Block) -> Result<Ledger, BlockchainError> {
+        // Someone else extended the chain while this block was being mined
+        let tip_hash = &self.chain.last().unwrap().hash;
+        if &new_block.prev_hash != tip_hash {
+            return Err(BlockchainError::StaleBlock {
+                index: new_block.index,
+            });
+        }
+
+        // The reward schedule and the receipts go by index, so it can't be made up
+        let expected = self.chain.len() as u32;
+        if new_block.index != expected {
+            return Err(BlockchainError::WrongIndex {
+                index: new_block.index,
+                expected,
+            });
+        }
+
+        // The stored hash must cover the final prev_hash set by `prepare_block`.
+        // A block mined before it was linked (or changed after mining) fails here.
+        if new_block.hash != new_block.calculate_hash(self.hash_algo) {
+            return Err(BlockchainError::InvalidHash {
+                index: new_block.index,
+            });
+        }
+
+        // The block has to be mined at the difficulty the chain asks for right now
+        if new_block.difficulty_bits != self.next_difficulty()
+            || !meets_difficulty(&new_block.hash, new_block.difficulty_bits)
+        {
+            return Err(BlockchainError::InsufficientWork {
+                index: new_block.index,
+            });
+        }
+
+        self.check_transactions(new_block, &self.ledger)
+    }
+
+    // The coinbase counts towards the limit like any other transaction
+    fn check_tx_count(&self, block: &Block) -> Result<(), BlockchainError> {
+        let tx_count = block.data.transaction_table.len();
+        if tx_count > self.max_tx_per_block {
+            return Err(BlockchainError::TooManyTransactions {
+                count: tx_count,
+                max: self.max_tx_per_block,
+            });
+        }
+        Ok(())
+    }
+
+    // 🎯 What the block does with the coins: it stays within the transaction
+    // limit, every transfer is signed by its sender, the coinbase pays no more
+    // than was earned, and on top of `ledger` (the balances and nonces before
+    // this block) nobody overspends or reuses a nonce. Returns the changes the
+    // block makes to `ledger`.
+    fn check_transactions(
+        &self,
+        block: &Block,
+        ledger: &Ledger,
+    ) -> Result<Ledger, BlockchainError> {
+        self.check_tx_count(block)?;
+        check_signatures(block)?;
+        self.check_coinbase(block)?;
+        ledger.block_changes(block)
+    }
+
+    // 🎯 A miner pays itself once, with the first transaction of the block, and
+    // no more than the block reward plus the fees of the other transactions.
+    // A block without a coinbase is allowed; its miner just gives the reward up.
+    fn check_coinbase(&self, block: &Block) -> Result<(), BlockchainError> {
+        let transactions = &block.data.transaction_table;
+        let invalid = || BlockchainError::InvalidCoinbase { index: block.index };
+        if transactions.iter().skip(1).any(|tx| tx.is_coinbase()) {
+            return Err(invalid());
+        }
+        let Some(coinbase) = transactions.first().filter(|tx| tx.is_coinbase()) else {
+            return Ok(());
+        };
+
+        if coinbase.amount > self.coinbase_amount(block.index, &transactions[1..])? {
+            return Err(invalid());
+        }
+        Ok(())
+    }
+
+    // Most the coinbase of the block at `height` may pay: the block reward for
+    // that height plus the fees of the block's other `transactions`
+    pub fn coinbase_amount(
+        &self,
+        height: u32,
+        transactions: &[Transaction],
+    ) -> Result<u64, BlockchainError> {
+        transactions
+            .iter()
+            .try_fold(self.rewards.reward_at(height), |total, tx| {
+                total.checked_add(tx.fee)
+            })
+            .ok_or_else(|| BlockchainError::Overflow("the block fees".to_string()))
+    }
+
+    // Add a block that already passed `check_next_block`, along with the ledger
+    // changes it returned
+    pub(crate) fn push_block(&mut self, new_block: Block, changes: Ledger) {
+        self.ledger.commit(changes);
+
+        // Index the transactions so receipts can be looked up by hash
+        for transaction in &new_block.data.transaction_table {
+            self.tx_index.insert(transaction.hash(), new_block.index);
+        }
+
+        // Add the block to the chain
+        self.chain.push(new_block);
+    }
+
+    // 🎯 The longest valid chain wins.
+    // Switch to `blocks` if they form a longer chain that starts from our genesis
+    // block, validates with our settings and never overdraws a balance.
+    // Returns whether we switched.
+    pub fn replace_chain(
+        &mut self,
+        blocks: Vec<Block>,
+        event_bus: &EventBus,
+    ) -> Result<bool, BlockchainError> {
+        if blocks.len() <= self.chain.len() {
+            return Ok(false);
+        }
+        // Both chains have to grow from our genesis block, otherwise a peer could
+        // hand itself any premine just by having mined more blocks
+        if blocks[0].hash != self.chain[0].hash {
+            return Err(BlockchainError::InvalidChain(
+                "it starts from a different genesis block".to_string(),
+            ));
+        }
+
+        let candidate = BlockChain::from_blocks(
+            blocks,
+            self.hash_algo,
+            self.max_tx_per_block,
+            self.difficulty,
+            self.rewards,
+        );
+        if !candidate.is_valid() {
+            return Err(BlockchainError::InvalidChain(
+                "a block has a bad hash, link, proof of work or transaction".to_string(),
+            ));
+        }
+
+        if let Some(store) = &self.store {
+            store.replace(&candidate)?;
+        }
+        self.chain = candidate.chain;
+        self.tx_index = candidate.tx_index;
+        self.ledger = candidate.ledger;
+
+        event_bus.broadcast(BlockchainEvent::BlockchainUpdated {
+            total_blocks: self.chain.len(),
+            total_transactions: self
+                .chain
+                .iter()
+                .map(|b| b.data.transaction_table.len())
+                .sum(),
+        });
+        Ok(true)
+    }
+
+    // Receipt for a mined transaction, or None if it is unknown or still pending
+    pub fn receipt(&self, tx_hash: &str) -> Option<TransactionReceipt> {
+        let block_index = *self.tx_index.get(tx_hash)?;
+        let block = self.chain.get(block_index as usize)?;
+        Some(TransactionReceipt {
+            tx_hash: tx_hash.to_string(),
+            block_index,
+            block_hash: block.hash.clone(),
+            confirmed: true,
+        })
+    }
+
+    // The block holding a mined transaction and its position in that block.
+    // If the exact same transaction was mined more than once, the latest one wins.
+    pub fn find_transaction(&self, tx_hash: &str) -> Option<(&Block, usize)> {
+        let block_index = *self.tx_index.get(tx_hash)?;
+        let block = self.chain.get(block_index as usize)?;
+        let position = block
+            .data
+            .transaction_table
+            .iter()
+            .position(|tx| tx.hash() == tx_hash)?;
+        Some((block, position))
+    }
+
+    // Every mined transaction that sent coins to or from `address`, oldest first,
+    // with the balance it left behind. Only the address's own transactions move
+    // its balance, so nothing else is replayed.
+    pub fn address_history(&self, address: &Address) -> Result<Vec<HistoryEntry>, BlockchainError> {
+        let overflow = || BlockchainError::Overflow(format!("the balance of {}", address));
+        let mut balance = 0u64;
+        let mut history = Vec::new();
+        for block in &self.chain {
+            for transaction in &block.data.transaction_table {
+                let (direction, counterparty) =
+                    match (transaction.from == *address, transaction.to == *address) {
+                        (true, true) => (Direction::ToSelf, &transaction.to),
+                        (true, false) => (Direction::Sent, &transaction.to),
+                        (false, true) => (Direction::Received, &transaction.from),
+                        (false, false) => continue,
+                    };
+                if transaction.from == *address && !transaction.is_coinbase() {
+                    balance = balance
+                        .checked_sub(transaction.total_cost()?)
+                        .ok_or_else(overflow)?;
+                }
+                if transaction.to == *address {
+                    balance = balance
+                        .checked_add(transaction.amount)
+                        .ok_or_else(overflow)?;
+                }
+                history.push(HistoryEntry {
+                    tx_hash: transaction.hash(),
+                    block_index: block.index,
+                    block_hash: block.hash.clone(),
+                    timestamp: block.timestamp,
+                    direction,
+                    counterparty: *counterparty,
+                    amount: transaction.amount,
+                    fee: transaction.fee,
+                    balance,
+                });
+            }
+        }
+        Ok(history)
+    }
+
+    // Serialize the chain as pretty-printed (readable) or compact (small) JSON
+    pub fn to_json(&self, pretty: bool) -> Result<String, BlockchainError> {
+        let json = if pretty {
+            serde_json::to_string_pretty(self)
+        } else {
+            serde_json::to_string(self)
+        };
+        json.map_err(|e| BlockchainError::StorageError(format!("Serialize Error : {}", e)))
+    }
+
+    // Parse a chain saved in either JSON layout and rebuild the transaction index
+    pub fn from_json(json: &str) -> Result<BlockChain, BlockchainError> {
+        let mut chain: BlockChain = serde_json::from_str(json)
+            .map_err(|e| BlockchainError::StorageError(format!("Parse Error : {}", e)))?;
+        chain.reindex();
+        Ok(chain)
+    }
+
+    // Rebuild the transaction hash -> block index lookup and the ledger from the
+    // blocks. A chain that doesn't replay gets an empty ledger; it fails
+    // `is_valid`, which everything loading a chain checks.
+    fn reindex(&mut self) {
+        self.ledger = Ledger::from_chain(self).unwrap_or_default();
+        self.tx_index = self
+            .chain
+            .iter()
+            .flat_map(|block| {
+                block
+                    .data
+                    .transaction_table
+                    .iter()
+                    .map(|tx| (tx.hash(), block.index))
+            })
+            .collect();
+    }
+
+    pub fn save_to_file(&self, path: &str, pretty: bool) -> Result<(), BlockchainError> {
+        let json = self.to_json(pretty)?;
+        File::create(path)
+            .and_then(|mut file| file.write_all(json.as_bytes()))
+            .map_err(|e| BlockchainError::StorageError(format!("Write Error : {}", e)))
+    }
+
+    pub fn load_from_file(path: &str) -> Result<BlockChain, BlockchainError> {
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| BlockchainError::StorageError(format!("Read Error : {}", e)))?;
+        Self::from_json(&json)
+    }
+
+    // Sum of every amount moved on the chain, fees and newly minted coins not included
+    pub fn total_traded(&self) -> Result<u64, BlockchainError> {
+        self.chain
+            .iter()
+            .flat_map(|block| &block.data.transaction_table)
+            .filter(|tx| !tx.is_coinbase())
+            .try_fold(0u64, |total, tx| {
+                total
+                    .checked_add(tx.amount)
+                    .ok_or_else(|| BlockchainError::Overflow("the total traded".to_string()))
+            })
+    }
+
+    // Difficulty the next block has to be mined at
+    pub fn next_difficulty(&self) -> u32 {
+        self.difficulty_after(&self.chain)
+    }
+
+    // Difficulty of the block that follows `blocks`. At every multiple of the
+    // retarget interval, compare how long the last interval took with the target.
+    fn difficulty_after(&self, blocks: &[Block]) -> u32 {
+        let Some(tip) = blocks.last() else {
+            return self.difficulty.initial_bits;
+        };
+        let interval = self.difficulty.retarget_interval as usize;
+        let next_index = blocks.len();
+        if interval == 0 || !next_index.is_multiple_of(interval) {
+            return tip.difficulty_bits;
+        }
+
+        // The genesis block wasn't mined and its timestamp is fixed, so it says
+        // nothing about block times: the first interval is timed from block 1
+        let first = (next_index - interval).max(1).min(next_index - 1);
+        let start = &blocks[first];
+        let elapsed = tip.timestamp.saturating_sub(start.timestamp);
+        self.difficulty.retarget(tip.difficulty_bits, elapsed)
+    }
+
+    pub fn get_total_block(&self) -> usize {
+        self.chain.len()
+    }
+
+    // Re-hash every block with the chain's algorithm and check the indexes, the
+    // links and the proof of work, replaying every retarget along the way, and
+    // replay the ledger to check every block's transactions like
+    // `check_next_block` does. The genesis block has no parent and isn't mined,
+    // so it only needs a matching hash.
+    pub fn is_valid(&self) -> bool {
+        let genesis_valid = self.chain.first().is_some_and(|genesis| {
+            genesis.index == 0
+                && genesis.prev_hash.is_empty()
+                && genesis.hash == genesis.calculate_hash(self.hash_algo)
+        });
+
+        let mut ledger = Ledger::new();
+        genesis_valid
+            && ledger.apply_block(&self.chain[0]).is_ok()
+            && (1..self.chain.len()).all(|i| {
+                let (prev, block) = (&self.chain[i - 1], &self.chain[i]);
+                block.index as usize == i
+                    && block.prev_hash == prev.hash
+                    && block.hash == block.calculate_hash(self.hash_algo)
+                    && block.difficulty_bits == self.difficulty_after(&self.chain[..i])
+                    && meets_difficulty(&block.hash, block.difficulty_bits)
+                    && self
+                        .check_transactions(block, &ledger)
+                        .map(|changes| ledger.commit(changes))
+                        .is_ok()
+            })
+    }
+}
+
+// Coins can only leave an address with its owner's signature. Coinbase
+// transactions mint new coins instead, so they aren't signed.
+fn check_signatures(block: &Block) -> Result<(), BlockchainError> {
+    match block
+        .data
+        .transaction_table
+        .iter()
+        .find(|tx| !tx.is_coinbase() && !wallet::verify_transaction(tx))
+    {
+        Some(forged) => Err(BlockchainError::InvalidSignature { from: forged.from }),
+        None => Ok(()),
+    }
+}
+
+// Mine a block without blocking readers: the chain is only read-locked to prepare
+// the block and write-locked for the final append. The proof of work itself runs
+// on a blocking thread so it does not stall the async runtime either.
+pub async fn mine_and_append(
+    blockchain: &tokio::sync::RwLock<BlockChain>,
+    new_block: Block,
+    event_bus: &EventBus,
+    miner: &str,
+) -> Result<(), BlockchainError> {
+    let (hash_algo, worker_pool, cancel) = {
+        let chain = blockchain.read().await;
+        (
+            chain.hash_algo,
+            chain.miner,
+            Arc::clone(&chain.mining_cancelled),
+        )
+    };
+
+    let mining_bus = event_bus.clone();
+    let miner_name = miner.to_string();
+    append_mined_with(blockchain, new_block, event_bus, move |block| {
+        worker_pool
+            .mine_until_cancelled(block, hash_algo, &mining_bus, &miner_name, &cancel)
+            .map(|_| ())
+    })
+    .await
+}
+
+// Everything around the proof of work, which `mine` does to the prepared block
+async fn append_mined_with(
+    blockchain: &tokio::sync::RwLock<BlockChain>,
+    new_block: Block,
+    event_bus: &EventBus,
+    mine: impl FnOnce(&mut Block) -> Result<(), BlockchainError> + Send + 'static,
+) -> Result<(), BlockchainError> {
+    let mut block = blockchain.read().await.prepare_block(new_block)?;
+
+    // The miner's threads would stall the async runtime, so they are started
+    // from a blocking task
+    let block = tokio::task::spawn_blocking(move || mine(&mut block).map(|()| block))
+        .await
+        .map_err(|e| BlockchainError::MiningFailed(e.to_string()))??;
+
+    blockchain.write().await.add_new_block(block, event_bus)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::COINBASE;
+    use crate::test_support::{address, mine_into, sample_block};
+    use crate::wallet::Wallet;
+    use std::time::Duration;
+
+    #[test]
+    fn validation_uses_the_configured_hash_algo() {
+        let mut chain = BlockChain::with_hash_algo(HashAlgo::Blake3).unwrap();
+        let block = Block::new(1, String::new(), sample_block().data).unwrap();
+        mine_into(&mut chain, block).unwrap();
+
+        assert!(chain.is_valid());
+        chain.hash_algo = HashAlgo::Sha256;
+        assert!(!chain.is_valid());
+    }
+
+    #[test]
+    fn block_within_tx_limit_is_accepted() {
+        let mut chain = BlockChain::new().unwrap().with_max_tx_per_block(1);
+        let block = Block::new(1, String::new(), sample_block().data).unwrap();
+
+        assert!(mine_into(&mut chain, block).is_ok());
+        assert_eq!(chain.get_total_block(), 2);
+    }
+
+    #[test]
+    fn block_over_tx_limit_is_rejected() {
+        let mut chain = BlockChain::new().unwrap().with_max_tx_per_block(1);
+        let mut data = sample_block().data;
+        data.transaction_table
+            .push(data.transaction_table[0].clone());
+        let block = Block::new(1, String::new(), data).unwrap();
+
+        let err = mine_into(&mut chain, block).unwrap_err();
+        assert!(matches!(
+            err,
+            BlockchainError::TooManyTransactions { count: 2, max: 1 }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "Block has 2 transactions but the limit is 1 per block"
+        );
+        assert_eq!(chain.get_total_block(), 1);
+    }
+
+    #[test]
+    fn block_with_forged_signature_is_rejected() {
+        let mut chain = BlockChain::new().unwrap();
+        let mut transaction =
+            Wallet::generate().create_signed_transaction(&address("bob"), 10, 1, 0);
+        transaction.to = address("mallory");
+        let data = MultipleTransactions {
+            transaction_table: vec![transaction],
+        };
+        let block = Block::new(1, String::new(), data).unwrap();
+
+        let err = mine_into(&mut chain, block).unwrap_err();
+        assert!(matches!(err, BlockchainError::InvalidSignature { .. }));
+    }
+
+    // The next block linked and mined like a peer would, without `prepare_block`
+    fn peer_block(chain: &BlockChain, transactions: Vec<Transaction>) -> Block {
+        let data = MultipleTransactions {
+            transaction_table: transactions,
+        };
+        let tip = chain.chain.last().unwrap();
+        let mut block = Block::new(tip.index + 1, tip.hash.clone(), data).unwrap();
+        block.difficulty_bits = chain.next_difficulty();
+        Miner::new(1).mine(&mut block, chain.hash_algo, &EventBus::new(), "peer");
+        block
+    }
+
+    #[test]
+    fn the_ledger_follows_appended_and_adopted_blocks() {
+        let mut chain = BlockChain::new()
+            .unwrap()
+            .with_genesis_allocations(&[(address("alice"), 100)]);
+        assert_eq!(chain.ledger.balance(&address("alice")), 100);
+
+        let mut longer = chain.clone();
+        let transfer =
+            Wallet::from_name("alice").create_signed_transaction(&address("bob"), 30, 0, 0);
+        let block = peer_block(&longer, vec![transfer]);
+        longer.add_new_block(block, &EventBus::new()).unwrap();
+        assert_eq!(longer.ledger.balance(&address("bob")), 30);
+
+        assert!(chain.replace_chain(longer.chain, &EventBus::new()).unwrap());
+        assert_eq!(chain.ledger.balance(&address("alice")), 70);
+        assert_eq!(chain.ledger.nonce(&address("alice")), 1);
+    }
+
+    #[test]
+    fn oversized_blocks_from_peers_are_rejected() {
+        let mut chain = BlockChain::new()
+            .unwrap()
+            .with_genesis_allocations(&[(address("alice"), 100)])
+            .with_max_tx_per_block(1);
+        let transfer =
+            Wallet::from_name("alice").create_signed_transaction(&address("bob"), 10, 0, 0);
+        let coinbase = Transaction::coinbase(&address("peer"), 10, 1);
+        let block = peer_block(&chain, vec![coinbase, transfer]);
+        assert!(matches!(
+            chain.add_new_block(block.clone(), &EventBus::new()),
+            Err(BlockchainError::TooManyTransactions { count: 2, max: 1 })
+        ));
+
+        chain.chain.push(block);
+        assert!(!chain.is_valid());
+        chain.max_tx_per_block = 2;
+        assert!(chain.is_valid());
+    }
+
+    #[test]
+    fn blocks_from_peers_need_signed_and_funded_transfers() {
+        let mut chain = BlockChain::new()
+            .unwrap()
+            .with_genesis_allocations(&[(address("alice"), 100)]);
+        let event_bus = EventBus::new();
+
+        // Nobody signed for alice's coins
+        let mut unsigned =
+            Wallet::from_name("alice").create_signed_transaction(&address("mallory"), 90, 0, 0);
+        unsigned.signature = None;
+        let block = peer_block(&chain, vec![unsigned]);
+        assert!(matches!(
+            chain.add_new_block(block, &event_bus),
+            Err(BlockchainError::InvalidSignature { .. })
+        ));
+
+        // Signed, but bob has nothing to send
+        let overdraft =
+            Wallet::from_name("bob").create_signed_transaction(&address("mallory"), 90, 0, 0);
+        let block = peer_block(&chain, vec![overdraft]);
+        assert!(matches!(
+            chain.add_new_block(block, &event_bus),
+            Err(BlockchainError::InsufficientFunds { .. })
+        ));
+
+        // Neither got in, so the ledger still replays
+        assert_eq!(chain.get_total_block(), 1);
+        assert_eq!(chain.ledger().balance(&address("alice")), 100);
+
+        // The same block slipped into the chain directly fails validation
+        let transfer =
+            Wallet::from_name("bob").create_signed_transaction(&address("mallory"), 90, 0, 0);
+        chain.chain.push(peer_block(&chain, vec![transfer]));
+        assert!(!chain.is_valid());
+    }
+
+    #[test]
+    fn a_transaction_replayed_in_a_peer_block_is_rejected() {
+        let new_chain = || {
+            BlockChain::new()
+                .unwrap()
+                .with_genesis_allocations(&[(address("alice"), 100)])
+        };
+        let event_bus = EventBus::new();
+        let transfer =
+            Wallet::from_name("alice").create_signed_transaction(&address("bob"), 10, 1, 0);
+        let replayed = |result| {
+            matches!(
+                result,
+                Err(BlockchainError::InvalidNonce {
+                    expected: 1,
+                    got: 0,
+                    ..
+                })
+            )
+        };
+
+        // Once is fine, again in a later block is not
+        let mut chain = new_chain();
+        let block = peer_block(&chain, vec![transfer.clone()]);
+        chain.add_new_block(block, &event_bus).unwrap();
+        let replay = peer_block(&chain, vec![transfer.clone()]);
+        assert!(replayed(chain.add_new_block(replay.clone(), &event_bus)));
+        assert_eq!(chain.get_total_block(), 2);
+
+        // Nor is a chain that has it twice
+        chain.chain.push(replay);
+        assert!(!chain.is_valid());
+
+        // Twice in the same block doesn't work either
+        let mut chain = new_chain();
+        let twice = peer_block(&chain, vec![transfer.clone(), transfer]);
+        assert!(replayed(chain.add_new_block(twice, &event_bus)));
+    }
+
+    #[test]
+    fn block_index_has_to_match_its_position() {
+        let mut chain = BlockChain::new().unwrap();
+        let mut block = sample_block();
+        block.index = 5;
+        assert!(matches!(
+            mine_into(&mut chain, block),
+            Err(BlockchainError::WrongIndex {
+                index: 5,
+                expected: 1
+            })
+        ));
+        mine_into(&mut chain, sample_block()).unwrap();
+
+        // Claiming a lower index, e.g. to dodge a halving, breaks the chain
+        let event_bus = EventBus::new();
+        let mut block = chain.prepare_block(sample_block()).unwrap();
+        block.mine_block_with_visual_hash(chain.hash_algo, &event_bus, "miner");
+        chain.chain.push(block);
+        assert!(!chain.is_valid());
+
+        chain.chain[2].index = 2;
+        chain.chain[2].mine_block_with_visual_hash(chain.hash_algo, &event_bus, "miner");
+        assert!(chain.is_valid());
+    }
+
+    #[test]
+    fn ledger_lists_every_block_with_short_hash_and_totals() {
+        let mut chain = BlockChain::new().unwrap();
+        mine_into(&mut chain, sample_block()).unwrap();
+        let mut second = sample_block();
+        second.index = 2;
+        mine_into(&mut chain, second).unwrap();
+
+        let ledger = chain.to_string();
+        let lines: Vec<&str> = ledger.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("Ledger: 3 blocks"));
+
+        for (line, block) in lines[1..].iter().zip(&chain.chain) {
+            assert!(line.starts_with(&format!("#{:<4}", block.index)));
+            if !block.hash.is_empty() {
+                assert!(line.contains(&format!("{}…", &block.hash[..12])));
+                assert!(!line.contains(&block.hash));
+            }
+        }
+        // Two blocks that each mint 10 coins
+        assert!(lines[3].ends_with("total         20  fees        0"));
+    }
+
+    #[test]
+    fn appended_blocks_link_to_their_parent_with_valid_hashes() {
+        let mut chain = BlockChain::new().unwrap();
+        for index in 1..=3 {
+            let mut block = sample_block();
+            block.index = index;
+            block.prev_hash = String::new();
+            mine_into(&mut chain, block).unwrap();
+        }
+
+        for pair in chain.chain.windows(2) {
+            let (prev, block) = (&pair[0], &pair[1]);
+            assert_eq!(block.prev_hash, prev.hash);
+            assert_eq!(block.hash, block.calculate_hash(chain.hash_algo));
+        }
+        assert!(chain.is_valid());
+    }
+
+    #[test]
+    fn fast_blocks_raise_the_difficulty_and_announce_it() {
+        let mut chain = BlockChain::new()
+            .unwrap()
+            .with_difficulty(DifficultyConfig {
+                initial_bits: 4,
+                retarget_interval: 2,
+                target_block_time_secs: 100,
+            });
+        let event_bus = EventBus::new();
+        let mut events = event_bus.subscribe();
+
+        // Every sample block has the same timestamp, so they look instant
+        for index in 1..=3 {
+            let mut block = chain.prepare_block(sample_block()).unwrap();
+            block.index = index;
+            block.mine_block_with_visual_hash(chain.hash_algo, &event_bus, "miner");
+            chain.add_new_block(block, &event_bus).unwrap();
+        }
+
+        let bits: Vec<u32> = chain.chain.iter().map(|b| b.difficulty_bits).collect();
+        assert_eq!(bits, vec![4, 4, 5, 5]);
+        assert_eq!(chain.next_difficulty(), 6);
+        assert!(chain.is_valid());
+
+        let mut adjustments = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            if let BlockchainEvent::DifficultyAdjusted {
+                block_index,
+                previous_bits,
+                difficulty_bits,
+            } = event
+            {
+                adjustments.push((block_index, previous_bits, difficulty_bits));
+            }
+        }
+        assert_eq!(adjustments, vec![(2, 4, 5), (4, 5, 6)]);
+
+        // Claiming a lower difficulty than the chain asks for is caught
+        chain.chain[3].difficulty_bits = 4;
+        assert!(!chain.is_valid());
+    }
+
+    #[test]
+    fn block_mined_below_the_required_difficulty_is_rejected() {
+        let mut chain = BlockChain::new()
+            .unwrap()
+            .with_difficulty(DifficultyConfig::fixed(12));
+        let event_bus = EventBus::new();
+
+        let mut block = chain.prepare_block(sample_block()).unwrap();
+        block.difficulty_bits = 1;
+        block.mine_block_with_visual_hash(chain.hash_algo, &event_bus, "miner");
+        // Find a nonce that is good enough for 1 bit but not for 12
+        while meets_difficulty(&block.hash, 12) {
+            block.nonce += 1;
+            block.hash = block.calculate_hash(chain.hash_algo);
+        }
+
+        assert!(matches!(
+            chain.add_new_block(block, &event_bus),
+            Err(BlockchainError::InsufficientWork { index: 1 })
+        ));
+        assert_eq!(chain.get_total_block(), 1);
+    }
+
+    #[test]
+    fn coinbase_may_claim_the_halved_reward_plus_fees_and_no_more() {
+        let mut chain = BlockChain::new()
+            .unwrap()
+            .with_rewards(RewardSchedule {
+                initial_reward: 100,
+                halving_interval: 2,
+            })
+            .with_genesis_allocations(&[(address("alice"), 100)]);
+
+        // Block 1 may pay 100 + the 1 coin fee of alice's transfer
+        let with_coinbase = |index: u32, amount: u64| {
+            let mut block = sample_block();
+            block.index = index;
+            // One transfer from alice per block, so block 2 holds her second
+            block.data.transaction_table = vec![
+                Transaction::coinbase(&address("miner"), amount, index),
+                Wallet::from_name("alice").create_signed_transaction(
+                    &address("bob"),
+                    10,
+                    1,
+                    index as u64 - 1,
+                ),
+            ];
+            block
+        };
+        mine_into(&mut chain, with_coinbase(1, 101)).unwrap();
+
+        // From block 2 on the reward is halved to 50
+        assert_eq!(chain.coinbase_amount(2, &[]).unwrap(), 50);
+        assert!(matches!(
+            mine_into(&mut chain, with_coinbase(2, 52)),
+            Err(BlockchainError::InvalidCoinbase { index: 2 })
+        ));
+
+        // Only the first transaction may mint coins
+        let mut block = with_coinbase(2, 51);
+        block
+            .data
+            .transaction_table
+            .push(Transaction::coinbase(&address("miner"), 1, 2));
+        assert!(matches!(
+            mine_into(&mut chain, block),
+            Err(BlockchainError::InvalidCoinbase { index: 2 })
+        ));
+
+        mine_into(&mut chain, with_coinbase(2, 51)).unwrap();
+        assert!(chain.is_valid());
+        assert_eq!(chain.ledger().balance(&address("miner")), 152);
+
+        // A coinbase edited afterwards (and re-hashed) no longer validates
+        chain.chain[2].data.transaction_table[0].amount = 1_000;
+        chain.chain[2].hash = chain.chain[2].calculate_hash(chain.hash_algo);
+        assert!(!chain.is_valid());
+    }
+
+    #[test]
+    fn history_lists_each_send_and_receive_with_the_balance_after_it() {
+        let mut chain = BlockChain::new()
+            .unwrap()
+            .with_genesis_allocations(&[(address("alice"), 100)]);
+        let transfer = |from: &str, to: &str, amount, fee, nonce| {
+            Wallet::from_name(from).create_signed_transaction(&address(to), amount, fee, nonce)
+        };
+        let mut block = sample_block();
+        block.data.transaction_table = vec![transfer("alice", "bob", 10, 1, 0)];
+        mine_into(&mut chain, block).unwrap();
+        let mut block = sample_block();
+        block.index = 2;
+        block.data.transaction_table = vec![
+            transfer("bob", "alice", 4, 0, 0),
+            transfer("alice", "alice", 5, 2, 1),
+        ];
+        mine_into(&mut chain, block).unwrap();
+
+        let summary = |name| -> Vec<(Direction, Address, u64)> {
+            chain
+                .address_history(&address(name))
+                .unwrap()
+                .into_iter()
+                .map(|entry| (entry.direction, entry.counterparty, entry.balance))
+                .collect()
+        };
+        assert_eq!(
+            summary("alice"),
+            vec![
+                (Direction::Received, COINBASE, 100),
+                (Direction::Sent, address("bob"), 89),
+                (Direction::Received, address("bob"), 93),
+                (Direction::ToSelf, address("alice"), 91),
+            ]
+        );
+        assert_eq!(summary("bob").len(), 2);
+        assert!(summary("nobody").is_empty());
+
+        let wanted = transfer("bob", "alice", 4, 0, 0).hash();
+        let (block, position) = chain.find_transaction(&wanted).unwrap();
+        assert_eq!((block.index, position), (2, 0));
+        assert!(chain.find_transaction("nope").is_none());
+    }
+
+    #[test]
+    fn rejects_block_hashed_before_prev_hash_was_set() {
+        let mut chain = BlockChain::new().unwrap();
+        mine_into(&mut chain, sample_block()).unwrap();
+
+        // Mined with the empty prev_hash from `Block::new`, then linked afterwards
+        let event_bus = EventBus::new();
+        let mut block = sample_block();
+        block.index = 2;
+        block.prev_hash = String::new();
+        block.mine_block_with_visual_hash(chain.hash_algo, &event_bus, "miner");
+        block.prev_hash = chain.chain.last().unwrap().hash.clone();
+
+        assert!(matches!(
+            chain.add_new_block(block, &event_bus),
+            Err(BlockchainError::InvalidHash { index: 2 })
+        ));
+        assert_eq!(chain.get_total_block(), 2);
+    }
+
+    #[test]
+    fn genesis_block_has_a_valid_hash() {
+        for algo in [HashAlgo::Sha256, HashAlgo::Sha3_256, HashAlgo::Blake3] {
+            let mut chain = BlockChain::with_hash_algo(algo).unwrap();
+            let genesis = &chain.chain[0];
+
+            assert!(!genesis.hash.is_empty());
+            assert_eq!(genesis.hash, genesis.calculate_hash(algo));
+            assert!(chain.is_valid());
+            // Created the same way, so another node gets the same one
+            assert_eq!(
+                BlockChain::with_hash_algo(algo).unwrap().chain[0].hash,
+                genesis.hash
+            );
+
+            // Tampering with the genesis block is now caught too
+            chain.chain[0].timestamp += 1;
+            assert!(!chain.is_valid());
+        }
+    }
+
+    #[test]
+    fn transaction_cost_overflow_is_detected() {
+        let mut transaction =
+            Wallet::from_name("alice").create_signed_transaction(&address("bob"), 10, 1, 0);
+        assert_eq!(transaction.total_cost().unwrap(), 11);
+
+        transaction.amount = u64::MAX - 5;
+        transaction.fee = 10;
+        assert!(matches!(
+            transaction.total_cost(),
+            Err(BlockchainError::Overflow(_))
+        ));
+
+        let mut block = sample_block();
+        block.data.transaction_table = vec![transaction];
+        let chain = BlockChain::new().unwrap();
+        assert!(matches!(
+            chain.prepare_block(block),
+            Err(BlockchainError::Overflow(_))
+        ));
+    }
+
+    #[test]
+    fn total_traded_overflow_is_detected() {
+        let mut chain = BlockChain::new().unwrap();
+        // Pushed straight onto the chain, so nothing checks the balances
+        let mut block = sample_block();
+        block.data.transaction_table = vec![Transaction {
+            from: address("alice"),
+            to: address("bob"),
+            amount: u64::MAX - 1,
+            fee: 0,
+            nonce: 0,
+            signature: None,
+            public_key: None,
+        }];
+        chain.chain.push(block.clone());
+        assert_eq!(chain.total_traded().unwrap(), u64::MAX - 1);
+
+        block.data.transaction_table[0].amount = 10;
+        chain.chain.push(block);
+        assert!(matches!(
+            chain.total_traded(),
+            Err(BlockchainError::Overflow(_))
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn reads_proceed_while_a_block_is_being_mined() {
+        let blockchain = Arc::new(tokio::sync::RwLock::new(BlockChain::new().unwrap()));
+        let hash_algo = blockchain.read().await.hash_algo;
+        let candidate = Block::new(1, String::new(), sample_block().data).unwrap();
+
+        // The proof of work waits for the test to let it go, so the chain is
+        // read while mining is known to be in progress
+        let (started, mining_started) = tokio::sync::oneshot::channel();
+        let (release, parked) = std::sync::mpsc::channel::<()>();
+        let miner_chain = Arc::clone(&blockchain);
+        let mining = tokio::spawn(async move {
+            append_mined_with(&miner_chain, candidate, &EventBus::new(), move |block| {
+                started.send(()).unwrap();
+                parked.recv().unwrap();
+                Miner::new(1).mine(block, hash_algo, &EventBus::new(), "miner");
+                Ok(())
+            })
+            .await
+        });
+        mining_started.await.unwrap();
+
+        let read = tokio::time::timeout(Duration::from_secs(5), blockchain.read())
+            .await
+            .expect("read lock was blocked by mining");
+        assert_eq!(read.get_total_block(), 1);
+        drop(read);
+        assert!(!mining.is_finished());
+
+        release.send(()).unwrap();
+        mining.await.unwrap().unwrap();
+        assert_eq!(blockchain.read().await.get_total_block(), 2);
+    }
+
+    #[tokio::test]
+    async fn cancelled_mining_appends_nothing() {
+        let chain = BlockChain::new().unwrap();
+        chain.cancel_mining();
+        let blockchain = tokio::sync::RwLock::new(chain);
+        let block = Block::new(1, String::new(), sample_block().data).unwrap();
+
+        assert!(matches!(
+            mine_and_append(&blockchain, block, &EventBus::new(), "miner").await,
+            Err(BlockchainError::MiningCancelled { index: 1 })
+        ));
+        assert_eq!(blockchain.read().await.get_total_block(), 1);
+    }
+
+    #[test]
+    fn mined_transaction_has_a_receipt() {
+        let wallet = Wallet::generate();
+        let mut chain = BlockChain::new()
+            .unwrap()
+            .with_genesis_allocations(&[(wallet.address(), 100)]);
+        let transaction = wallet.create_signed_transaction(&address("bob"), 10, 1, 0);
+        let tx_hash = transaction.hash();
+        let data = MultipleTransactions {
+            transaction_table: vec![transaction],
+        };
+        mine_into(&mut chain, Block::new(1, String::new(), data).unwrap()).unwrap();
+
+        let receipt = chain.receipt(&tx_hash).unwrap();
+        assert_eq!(receipt.block_index, 1);
+        assert_eq!(receipt.block_hash, chain.chain[1].hash);
+        assert!(receipt.confirmed);
+    }
+
+    #[test]
+    fn unknown_transaction_has_no_receipt() {
+        let chain = BlockChain::new().unwrap();
+        assert_eq!(chain.receipt("not-a-real-hash"), None);
+    }
+
+    #[test]
+    fn pretty_and_compact_json_load_to_the_same_chain() {
+        let wallet = Wallet::generate();
+        let mut chain = BlockChain::with_hash_algo(HashAlgo::Sha3_256)
+            .unwrap()
+            .with_genesis_allocations(&[(wallet.address(), 100)]);
+        let transaction = wallet.create_signed_transaction(&address("bob"), 10, 1, 0);
+        let tx_hash = transaction.hash();
+        let data = MultipleTransactions {
+            transaction_table: vec![transaction],
+        };
+        mine_into(&mut chain, Block::new(1, String::new(), data).unwrap()).unwrap();
+
+        let pretty = chain.to_json(true).unwrap();
+        let compact = chain.to_json(false).unwrap();
+        assert!(pretty.contains('\n'));
+        assert!(!compact.contains('\n'));
+        assert!(compact.len() < pretty.len());
+
+        let from_pretty = BlockChain::from_json(&pretty).unwrap();
+        let from_compact = BlockChain::from_json(&compact).unwrap();
+        assert_eq!(from_pretty.to_json(false).unwrap(), compact);
+        assert_eq!(from_compact.to_json(false).unwrap(), compact);
+        assert!(from_compact.is_valid());
+        assert_eq!(from_compact.hash_algo, HashAlgo::Sha3_256);
+        assert_eq!(from_compact.receipt(&tx_hash), chain.receipt(&tx_hash));
+    }
+}
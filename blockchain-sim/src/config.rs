@@ -0,0 +1,309 @@
+use crate::difficulty::{
+    DEFAULT_DIFFICULTY_BITS, DEFAULT_RETARGET_INTERVAL, DEFAULT_TARGET_BLOCK_TIME_SECS,
+    MAX_DIFFICULTY_BITS, MIN_DIFFICULTY_BITS,
+};
+use crate::reward::{DEFAULT_HALVING_INTERVAL, INITIAL_BLOCK_REWARD};
+use crate::storage::DEFAULT_DATA_DIR;
+use crate::{BlockchainError, DifficultyConfig, HashAlgo, MAX_TX_PER_BLOCK, Miner, RewardSchedule};
+use serde::{Deserialize, Deserializer};
+use std::fmt::Display;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::Path;
+use std::str::FromStr;
+
+// 🎯 Where do the settings come from?
+// Three layers, each overriding the one before: the defaults below, the
+// `blockchain.toml` file, and environment variables (command line flags such as
+// `serve --ws-port` win over all of them). Every key in the file is optional,
+// so it only needs the settings you want to change.
+//
+// Chain settings only shape a new chain. A stored chain keeps the settings it
+// was created with, otherwise its old blocks would stop validating.
+
+pub const DEFAULT_CONFIG_FILE: &str = "blockchain.toml";
+pub const DEFAULT_WS_PORT: u16 = 8080;
+pub const DEFAULT_API_PORT: u16 = 3000;
+pub const DEFAULT_STARTING_BALANCE: u64 = 10_000;
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub chain: ChainConfig,
+    pub mining: MiningConfig,
+    pub storage: StorageConfig,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    // Address the WebSocket and HTTP servers bind to
+    pub host: IpAddr,
+    pub ws_port: u16,
+    pub api_port: u16,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChainConfig {
+    // Written the same way as HASH_ALGO: sha256, sha3 or blake3
+    #[serde(deserialize_with = "from_str")]
+    pub hash_algo: HashAlgo,
+    pub max_tx_per_block: usize,
+    pub difficulty_bits: u32,
+    pub retarget_interval: u32,
+    // Target time between blocks; `serve` also checks for new transactions this often
+    pub block_interval_secs: u64,
+    pub block_reward: u64,
+    pub halving_interval: u32,
+    // What each demo wallet gets in the genesis block
+    pub starting_balance: u64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MiningConfig {
+    // Threads that search for nonces, 0 = one per CPU core
+    pub threads: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    pub data_dir: String,
+    pub event_log: String,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            host: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            ws_port: DEFAULT_WS_PORT,
+            api_port: DEFAULT_API_PORT,
+        }
+    }
+}
+
+impl Default for ChainConfig {
+    fn default() -> Self {
+        Self {
+            hash_algo: HashAlgo::default(),
+            max_tx_per_block: MAX_TX_PER_BLOCK,
+            difficulty_bits: DEFAULT_DIFFICULTY_BITS,
+            retarget_interval: DEFAULT_RETARGET_INTERVAL,
+            block_interval_secs: DEFAULT_TARGET_BLOCK_TIME_SECS,
+            block_reward: INITIAL_BLOCK_REWARD,
+            halving_interval: DEFAULT_HALVING_INTERVAL,
+            starting_balance: DEFAULT_STARTING_BALANCE,
+        }
+    }
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            data_dir: DEFAULT_DATA_DIR.to_string(),
+            event_log: crate::events::DEFAULT_EVENT_LOG.to_string(),
+        }
+    }
+}
+
+// Read a TOML string with the type's own parser, e.g. "blake3" for HashAlgo
+fn from_str<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    let value = String::deserialize(deserializer)?;
+    value.parse().map_err(serde::de::Error::custom)
+}
+
+fn config_error(context: impl Display, e: impl Display) -> BlockchainError {
+    BlockchainError::ConfigError(format!("{} : {}", context, e))
+}
+
+impl Config {
+    // `path` must exist. Without one, blockchain.toml is read if it is there.
+    // Environment variables are applied on top either way.
+    pub fn load(path: Option<&Path>) -> Result<Config, BlockchainError> {
+        let default_path = Path::new(DEFAULT_CONFIG_FILE);
+        let mut config = match path {
+            Some(path) => Self::from_file(path)?,
+            None if default_path.exists() => Self::from_file(default_path)?,
+            None => Config::default(),
+        };
+        config.apply_env(|key| std::env::var(key).ok())?;
+        Ok(config)
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Config, BlockchainError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|e| config_error(path.display(), e))?;
+        Self::parse(&text, path.display())
+    }
+
+    pub fn from_toml(text: &str) -> Result<Config, BlockchainError> {
+        Self::parse(text, "Parse Error")
+    }
+
+    fn parse(text: &str, context: impl Display) -> Result<Config, BlockchainError> {
+        let config: Config = toml::from_str(text).map_err(|e| config_error(context, e))?;
+        config.check()?;
+        Ok(config)
+    }
+
+    // Override settings from environment variables; `var` looks one up by name.
+    // A variable that is set but can't be parsed is an error, not silently ignored.
+    pub fn apply_env(
+        &mut self,
+        var: impl Fn(&str) -> Option<String>,
+    ) -> Result<(), BlockchainError> {
+        fn set<T: FromStr>(
+            var: &impl Fn(&str) -> Option<String>,
+            key: &str,
+            target: &mut T,
+        ) -> Result<(), BlockchainError>
+        where
+            T::Err: Display,
+        {
+            if let Some(value) = var(key) {
+                *target = value.trim().parse().map_err(|e| config_error(key, e))?;
+            }
+            Ok(())
+        }
+
+        set(&var, "HOST", &mut self.server.host)?;
+        set(&var, "WS_PORT", &mut self.server.ws_port)?;
+        set(&var, "API_PORT", &mut self.server.api_port)?;
+        set(&var, "HASH_ALGO", &mut self.chain.hash_algo)?;
+        set(&var, "MAX_TX_PER_BLOCK", &mut self.chain.max_tx_per_block)?;
+        set(&var, "DIFFICULTY_BITS", &mut self.chain.difficulty_bits)?;
+        set(&var, "RETARGET_INTERVAL", &mut self.chain.retarget_interval)?;
+        set(
+            &var,
+            "BLOCK_INTERVAL_SECS",
+            &mut self.chain.block_interval_secs,
+        )?;
+        set(&var, "BLOCK_REWARD", &mut self.chain.block_reward)?;
+        set(&var, "HALVING_INTERVAL", &mut self.chain.halving_interval)?;
+        set(&var, "STARTING_BALANCE", &mut self.chain.starting_balance)?;
+        set(&var, "MINING_THREADS", &mut self.mining.threads)?;
+        set(&var, "DATA_DIR", &mut self.storage.data_dir)?;
+        set(&var, "EVENT_LOG", &mut self.storage.event_log)?;
+        self.check()
+    }
+
+    fn check(&self) -> Result<(), BlockchainError> {
+        let bits = self.chain.difficulty_bits;
+        if !(MIN_DIFFICULTY_BITS..=MAX_DIFFICULTY_BITS).contains(&bits) {
+            return Err(config_error(
+                "difficulty_bits",
+                format!(
+                    "{} is outside {}..={}",
+                    bits, MIN_DIFFICULTY_BITS, MAX_DIFFICULTY_BITS
+                ),
+            ));
+        }
+        if self.chain.block_interval_secs == 0 {
+            return Err(config_error("block_interval_secs", "must be at least 1"));
+        }
+        // The coinbase alone needs one slot
+        if self.chain.max_tx_per_block == 0 {
+            return Err(config_error("max_tx_per_block", "must be at least 1"));
+        }
+        Ok(())
+    }
+
+    pub fn ws_addr(&self) -> SocketAddr {
+        SocketAddr::new(self.server.host, self.server.ws_port)
+    }
+
+    pub fn api_addr(&self) -> SocketAddr {
+        SocketAddr::new(self.server.host, self.server.api_port)
+    }
+
+    pub fn difficulty(&self) -> DifficultyConfig {
+        DifficultyConfig {
+            initial_bits: self.chain.difficulty_bits,
+            retarget_interval: self.chain.retarget_interval,
+            target_block_time_secs: self.chain.block_interval_secs,
+        }
+    }
+
+    pub fn miner(&self) -> Miner {
+        Miner::new(self.mining.threads)
+    }
+
+    pub fn rewards(&self) -> RewardSchedule {
+        RewardSchedule {
+            initial_reward: self.chain.block_reward,
+            halving_interval: self.chain.halving_interval,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn file_settings_override_the_defaults_and_env_overrides_the_file() {
+        let mut config = Config::from_toml(
+            r#"
+            [server]
+            ws_port = 9080
+
+            [chain]
+            hash_algo = "blake3"
+            block_reward = 100
+
+            [mining]
+            threads = 2
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.server.ws_port, 9080);
+        assert_eq!(config.server.api_port, DEFAULT_API_PORT);
+        assert_eq!(config.chain.hash_algo, HashAlgo::Blake3);
+        assert_eq!(config.rewards().initial_reward, 100);
+        assert_eq!(config.miner().threads(), 2);
+        assert_eq!(config.storage, StorageConfig::default());
+
+        let env = HashMap::from([
+            ("WS_PORT", "7000"),
+            ("DATA_DIR", "/tmp/chain"),
+            ("MINING_THREADS", "3"),
+        ]);
+        config
+            .apply_env(|key| env.get(key).map(|value| value.to_string()))
+            .unwrap();
+        assert_eq!(config.ws_addr().to_string(), "127.0.0.1:7000");
+        assert_eq!(config.storage.data_dir, "/tmp/chain");
+        assert_eq!(config.chain.hash_algo, HashAlgo::Blake3);
+        assert_eq!(config.mining.threads, 3);
+    }
+
+    #[test]
+    fn shipped_blockchain_toml_lists_the_defaults() {
+        let shipped = Config::from_toml(include_str!("../blockchain.toml")).unwrap();
+        assert_eq!(shipped, Config::default());
+    }
+
+    #[test]
+    fn bad_settings_are_reported() {
+        assert!(Config::from_toml("[server]\nws_port = \"eighty\"").is_err());
+        assert!(Config::from_toml("[chain]\nhash_algo = \"md5\"").is_err());
+        assert!(Config::from_toml("[chain]\ndifficulty_bits = 99").is_err());
+        assert!(Config::from_toml("[chain]\nmax_tx_per_block = 0").is_err());
+        // A typo shouldn't be silently ignored
+        assert!(Config::from_toml("[server]\nws_prot = 9080").is_err());
+
+        let mut config = Config::default();
+        assert!(matches!(
+            config.apply_env(|key| (key == "API_PORT").then(|| "99999".to_string())),
+            Err(BlockchainError::ConfigError(msg)) if msg.starts_with("API_PORT")
+        ));
+    }
+}
@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+
+// 🎯 What is Difficulty?
+// A block only counts as mined when its hash starts with enough zero bits.
+// Every extra bit halves the number of hashes that qualify, so it doubles the
+// work needed on average. 8 bits is the same as "the hash starts with 00".
+//
+// Miners get faster (or slower), so every few blocks the chain looks at how
+// long they actually took and adds or removes a bit to stay near the target time.
+
+pub const DEFAULT_DIFFICULTY_BITS: u32 = 8;
+pub const MIN_DIFFICULTY_BITS: u32 = 1;
+// A SHA-256 hash has 256 bits, but anything near this is already far too slow here
+pub const MAX_DIFFICULTY_BITS: u32 = 32;
+pub const DEFAULT_RETARGET_INTERVAL: u32 = 10;
+pub const DEFAULT_TARGET_BLOCK_TIME_SECS: u64 = 10;
+
+// Chosen when the chain is created and stored with it, so every node
+// (and every reload) replays exactly the same adjustments
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DifficultyConfig {
+    // Difficulty of the first mined block
+    pub initial_bits: u32,
+    // Retarget every this many blocks; 0 keeps the difficulty fixed
+    pub retarget_interval: u32,
+    // How long a block should take on average
+    pub target_block_time_secs: u64,
+}
+
+impl Default for DifficultyConfig {
+    fn default() -> Self {
+        Self {
+            initial_bits: DEFAULT_DIFFICULTY_BITS,
+            retarget_interval: DEFAULT_RETARGET_INTERVAL,
+            target_block_time_secs: DEFAULT_TARGET_BLOCK_TIME_SECS,
+        }
+    }
+}
+
+impl DifficultyConfig {
+    // Never retargets
+    pub fn fixed(bits: u32) -> Self {
+        Self {
+            initial_bits: bits.clamp(MIN_DIFFICULTY_BITS, MAX_DIFFICULTY_BITS),
+            retarget_interval: 0,
+            target_block_time_secs: DEFAULT_TARGET_BLOCK_TIME_SECS,
+        }
+    }
+
+    // Chains saved before difficulty was configurable were all mined at 8 bits
+    pub(crate) fn legacy() -> Self {
+        Self::fixed(DEFAULT_DIFFICULTY_BITS)
+    }
+
+    // One bit up when the last `retarget_interval` blocks came in more than twice
+    // as fast as planned, one bit down when they took more than twice as long
+    pub fn retarget(&self, bits: u32, elapsed_secs: u64) -> u32 {
+        let expected = self
+            .target_block_time_secs
+            .saturating_mul(u64::from(self.retarget_interval));
+        if elapsed_secs < expected / 2 {
+            (bits + 1).min(MAX_DIFFICULTY_BITS)
+        } else if elapsed_secs > expected.saturating_mul(2) {
+            bits.saturating_sub(1).max(MIN_DIFFICULTY_BITS)
+        } else {
+            bits
+        }
+    }
+}
+
+// Number of zero bits at the start of a hex encoded hash
+pub fn leading_zero_bits(hash: &str) -> u32 {
+    let mut bits = 0;
+    for digit in hash.chars() {
+        match digit.to_digit(16) {
+            Some(0) => bits += 4,
+            // A nibble has 4 bits; the u32 holding it has 28 more zeros in front
+            Some(value) => return bits + value.leading_zeros() - 28,
+            None => return bits,
+        }
+    }
+    bits
+}
+
+pub fn meets_difficulty(hash: &str, bits: u32) -> bool {
+    !hash.is_empty() && leading_zero_bits(hash) >= bits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_leading_zero_bits_of_a_hex_hash() {
+        assert_eq!(leading_zero_bits("ff"), 0);
+        assert_eq!(leading_zero_bits("7f"), 1);
+        assert_eq!(leading_zero_bits("1f"), 3);
+        assert_eq!(leading_zero_bits("0f"), 4);
+        assert_eq!(leading_zero_bits("00a"), 8);
+        assert_eq!(leading_zero_bits("003"), 10);
+
+        // 8 bits is exactly the old "starts with 00" rule
+        assert!(meets_difficulty("00ab", 8));
+        assert!(!meets_difficulty("0fab", 8));
+        assert!(!meets_difficulty("", 0));
+    }
+
+    #[test]
+    fn retargets_by_one_bit_towards_the_block_time() {
+        let config = DifficultyConfig {
+            initial_bits: 8,
+            retarget_interval: 10,
+            target_block_time_secs: 10,
+        };
+        // 100 seconds were expected for 10 blocks
+        assert_eq!(config.retarget(8, 20), 9);
+        assert_eq!(config.retarget(8, 100), 8);
+        assert_eq!(config.retarget(8, 250), 7);
+
+        assert_eq!(config.retarget(MAX_DIFFICULTY_BITS, 0), MAX_DIFFICULTY_BITS);
+        assert_eq!(
+            config.retarget(MIN_DIFFICULTY_BITS, 1_000),
+            MIN_DIFFICULTY_BITS
+        );
+    }
+}
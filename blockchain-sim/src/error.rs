@@ -0,0 +1,148 @@
+use crate::Address;
+use std::fmt;
+
+// 🎯 Everything that can go wrong while building, mining or storing the chain
+#[derive(Debug)]
+pub enum BlockchainError {
+    TimeError(String),
+    TooManyTransactions {
+        count: usize,
+        max: usize,
+    },
+    InvalidSignature {
+        from: Address,
+    },
+    DuplicateTransaction {
+        tx_hash: String,
+    },
+    StaleBlock {
+        index: u32,
+    },
+    InvalidHash {
+        index: u32,
+    },
+    InsufficientWork {
+        index: u32,
+    },
+    // A block's index has to be its position in the chain
+    WrongIndex {
+        index: u32,
+        expected: u32,
+    },
+    InvalidCoinbase {
+        index: u32,
+    },
+    // Mining was stopped (on shutdown) before a nonce was found
+    MiningCancelled {
+        index: u32,
+    },
+    MiningFailed(String),
+    InvalidChain(String),
+    InsufficientFunds {
+        address: Address,
+        balance: u64,
+        needed: u64,
+    },
+    // Not a base58check address, or one with a bad checksum
+    InvalidAddress {
+        address: String,
+        reason: String,
+    },
+    // The sender's transactions have to be numbered 0, 1, 2, ... without gaps
+    InvalidNonce {
+        address: Address,
+        expected: u64,
+        got: u64,
+    },
+    Overflow(String),
+    StorageError(String),
+    ConfigError(String),
+}
+
+impl fmt::Display for BlockchainError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlockchainError::TimeError(msg) => write!(f, "{}", msg),
+            BlockchainError::TooManyTransactions { count, max } => write!(
+                f,
+                "Block has {} transactions but the limit is {} per block",
+                count, max
+            ),
+            BlockchainError::InvalidSignature { from } => {
+                write!(f, "Transaction from {} has an invalid signature", from)
+            }
+            BlockchainError::DuplicateTransaction { tx_hash } => {
+                write!(f, "Transaction {} is already pending", tx_hash)
+            }
+            BlockchainError::StorageError(msg) => write!(f, "{}", msg),
+            BlockchainError::ConfigError(msg) => write!(f, "Configuration error: {}", msg),
+            BlockchainError::StaleBlock { index } => write!(
+                f,
+                "Block {} was mined on top of a block that is no longer the tip",
+                index
+            ),
+            BlockchainError::Overflow(what) => {
+                write!(f, "Arithmetic overflow while computing {}", what)
+            }
+            BlockchainError::InvalidHash { index } => write!(
+                f,
+                "Block {} has a hash that does not match its contents",
+                index
+            ),
+            BlockchainError::InvalidChain(reason) => {
+                write!(f, "Received chain is not valid: {}", reason)
+            }
+            BlockchainError::InsufficientWork { index } => write!(
+                f,
+                "Block {} was not mined at the difficulty the chain requires",
+                index
+            ),
+            BlockchainError::WrongIndex { index, expected } => write!(
+                f,
+                "Block claims index {} but would be block {} of the chain",
+                index, expected
+            ),
+            BlockchainError::InvalidCoinbase { index } => write!(
+                f,
+                "Block {} pays its miner more than the block reward plus fees",
+                index
+            ),
+            BlockchainError::MiningCancelled { index } => {
+                write!(f, "Mining block {} was cancelled", index)
+            }
+            BlockchainError::MiningFailed(msg) => write!(f, "Mining failed: {}", msg),
+            BlockchainError::InsufficientFunds {
+                address,
+                balance,
+                needed,
+            } => write!(
+                f,
+                "{} has {} but the transaction needs {}",
+                address, balance, needed
+            ),
+            BlockchainError::InvalidAddress { address, reason } => {
+                write!(f, "{:?} is not a valid address: {}", address, reason)
+            }
+            BlockchainError::InvalidNonce {
+                address,
+                expected,
+                got,
+            } if got < expected => write!(
+                f,
+                "Nonce {} of {} was already used; the next one is {}",
+                got, address, expected
+            ),
+            BlockchainError::InvalidNonce {
+                address,
+                expected,
+                got,
+            } => write!(
+                f,
+                "Nonce {} of {} skips ahead; the next one is {}",
+                got, address, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BlockchainError {}
@@ -1,7 +1,15 @@
+use crate::{Address, Transaction};
 use serde::Serialize;
-use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::broadcast;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::fs::OpenOptions;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{Notify, broadcast};
+use tokio::task::JoinHandle;
 use uuid::Uuid;
 
 // 🎯 What are Events?
@@ -24,11 +32,16 @@ pub enum BlockchainEvent {
         miner: String,
         timestamp: u64,
         transactions_count: usize,
+        // How many nonces were hashed, how long that took and the resulting
+        // hashes per second over all mining threads
+        hash_attempts: u64,
+        mining_millis: u64,
+        hash_rate: u64,
     },
     // When a new transaction is created
     TransactionCreated {
-        from: String,
-        to: String,
+        from: Address,
+        to: Address,
         amount: u64,
         fee: u64,
         block_index: u32,
@@ -38,6 +51,48 @@ pub enum BlockchainEvent {
         total_blocks: usize,
         total_transactions: usize,
     },
+    // When a signed transaction is accepted into the mempool
+    TransactionSubmitted {
+        transaction: Transaction,
+    },
+    // When a transaction is turned away from the mempool, or dropped from it
+    // because it can no longer be mined (e.g. its nonce was used in the meantime)
+    TransactionRejected {
+        tx_hash: String,
+        from: Address,
+        reason: String,
+    },
+    // When a retarget changes the difficulty, starting with block `block_index`
+    DifficultyAdjusted {
+        block_index: u32,
+        previous_bits: u32,
+        difficulty_bits: u32,
+    },
+}
+
+impl BlockchainEvent {
+    // The names clients use to pick events, the same as the JSON key of each event
+    pub const NAMES: [&'static str; 7] = [
+        "BlockMiningStarted",
+        "BlockMined",
+        "TransactionCreated",
+        "BlockchainUpdated",
+        "TransactionSubmitted",
+        "TransactionRejected",
+        "DifficultyAdjusted",
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BlockchainEvent::BlockMiningStarted { .. } => "BlockMiningStarted",
+            BlockchainEvent::BlockMined { .. } => "BlockMined",
+            BlockchainEvent::TransactionCreated { .. } => "TransactionCreated",
+            BlockchainEvent::BlockchainUpdated { .. } => "BlockchainUpdated",
+            BlockchainEvent::TransactionSubmitted { .. } => "TransactionSubmitted",
+            BlockchainEvent::TransactionRejected { .. } => "TransactionRejected",
+            BlockchainEvent::DifficultyAdjusted { .. } => "DifficultyAdjusted",
+        }
+    }
 }
 
 // 🎯 What is a Broadcast Channel?
@@ -51,23 +106,47 @@ pub type EventReceiver = broadcast::Receiver<BlockchainEvent>;
 // This keeps track of all the people (clients) who are connected to our WebSocket.
 // Like a guest list at a party!
 
+// How many events may wait for one client before the oldest are thrown away
+pub const CLIENT_QUEUE_CAPACITY: usize = 64;
+
 #[derive(Debug)]
 pub struct ConnectionManager {
-    connections: Arc<tokio::sync::RwLock<HashMap<Uuid, ()>>>,
+    connections: Arc<tokio::sync::RwLock<HashMap<Uuid, Arc<ClientQueue>>>>,
+    queue_capacity: usize,
+    // Events thrown away because a client couldn't keep up
+    dropped_events: Arc<AtomicU64>,
+}
+
+impl Default for ConnectionManager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl ConnectionManager {
     pub fn new() -> Self {
         Self {
             connections: Arc::new(tokio::sync::RwLock::new(HashMap::new())),
+            queue_capacity: CLIENT_QUEUE_CAPACITY,
+            dropped_events: Arc::new(AtomicU64::new(0)),
         }
     }
 
-    // Add a new client connection
-    pub async fn add_connection(&self, id: Uuid) {
+    pub fn with_queue_capacity(mut self, capacity: usize) -> Self {
+        self.queue_capacity = capacity.max(1);
+        self
+    }
+
+    // Add a new client connection; its events show up in the returned queue
+    pub async fn add_connection(&self, id: Uuid) -> Arc<ClientQueue> {
+        let queue = Arc::new(ClientQueue::new(
+            self.queue_capacity,
+            Arc::clone(&self.dropped_events),
+        ));
         let mut connections = self.connections.write().await;
-        connections.insert(id, ());
+        connections.insert(id, Arc::clone(&queue));
         println!("🟢 New client connected: {}", id);
+        queue
     }
 
     // Remove a client connection
@@ -82,6 +161,160 @@ impl ConnectionManager {
         let connections = self.connections.read().await;
         connections.len()
     }
+
+    // How many events slow clients missed since the manager was created
+    pub fn dropped_events(&self) -> u64 {
+        self.dropped_events.load(Ordering::Relaxed)
+    }
+
+    // Copy every event from the bus into each client's queue. Pushing never
+    // waits, so this task keeps up with the bus however slow a client is.
+    // When the bus closes, every queue is closed too.
+    pub fn forward_events(&self, mut receiver: EventReceiver) -> JoinHandle<()> {
+        let connections = Arc::clone(&self.connections);
+        tokio::spawn(async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => {
+                        for queue in connections.read().await.values() {
+                            queue.push(event.clone());
+                        }
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        eprintln!("⚠️ Event forwarder fell behind, {} events skipped", skipped);
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+            for queue in connections.read().await.values() {
+                queue.close();
+            }
+        })
+    }
+}
+
+// 🎯 What is an Outbound Queue?
+// Every client gets its own small mailbox of events waiting to be sent. When a
+// client on a slow connection lets its mailbox fill up, the oldest event is
+// thrown away to make room, so it only ever misses old news and nobody else
+// has to wait for it. A new `BlockchainUpdated` replaces the one still waiting,
+// since only the latest totals matter.
+
+#[derive(Debug)]
+pub struct ClientQueue {
+    capacity: usize,
+    state: Mutex<QueueState>,
+    wake: Notify,
+    dropped_events: Arc<AtomicU64>,
+}
+
+#[derive(Debug, Default)]
+struct QueueState {
+    events: VecDeque<BlockchainEvent>,
+    // Names of the events the client wants; `None` means all of them
+    filter: Option<HashSet<&'static str>>,
+    closed: bool,
+}
+
+impl ClientQueue {
+    fn new(capacity: usize, dropped_events: Arc<AtomicU64>) -> Self {
+        Self {
+            capacity,
+            state: Mutex::new(QueueState::default()),
+            wake: Notify::new(),
+            dropped_events,
+        }
+    }
+
+    // Only queue events with these names from now on; `None` queues everything
+    pub fn set_filter(&self, filter: Option<HashSet<&'static str>>) {
+        self.state.lock().unwrap().filter = filter;
+    }
+
+    fn push(&self, event: BlockchainEvent) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(filter) = &state.filter
+            && !filter.contains(event.name())
+        {
+            return;
+        }
+        if matches!(event, BlockchainEvent::BlockchainUpdated { .. }) {
+            state
+                .events
+                .retain(|queued| !matches!(queued, BlockchainEvent::BlockchainUpdated { .. }));
+        }
+        if state.events.len() >= self.capacity {
+            state.events.pop_front();
+            self.dropped_events.fetch_add(1, Ordering::Relaxed);
+        }
+        state.events.push_back(event);
+        drop(state);
+        self.wake.notify_one();
+    }
+
+    fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.wake.notify_one();
+    }
+
+    // The next event to send, waiting for one if the queue is empty.
+    // `None` once the bus has closed and everything queued was taken.
+    pub async fn next(&self) -> Option<BlockchainEvent> {
+        loop {
+            {
+                let mut state = self.state.lock().unwrap();
+                if let Some(event) = state.events.pop_front() {
+                    return Some(event);
+                }
+                if state.closed {
+                    return None;
+                }
+            }
+            self.wake.notified().await;
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// 🎯 What is a Retry Queue?
+// Sometimes a message can't be delivered right now - for example a dashboard is
+// reconnecting. Instead of throwing the event away, we park it in a small queue
+// and try again a few times. If it still can't be delivered, we give up and
+// count it as dropped so we can see how often that happens.
+
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    // How many times a failed event is re-sent before it is dropped
+    pub max_retries: u32,
+    // How long to wait between attempts
+    pub delay: Duration,
+    // How many events may wait in the queue at once
+    pub capacity: usize,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            delay: Duration::from_millis(500),
+            capacity: 100,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct RetryQueue {
+    policy: RetryPolicy,
+    // Each entry remembers how many retries it has already used
+    pending: Arc<Mutex<VecDeque<(BlockchainEvent, u32)>>>,
+    wake: Arc<Notify>,
 }
 
 // 🎯 What is an Event Bus?
@@ -92,12 +325,47 @@ impl ConnectionManager {
 #[derive(Debug, Clone)]
 pub struct EventBus {
     pub sender: EventSender,
+    retry: Option<RetryQueue>,
+    dropped_events: Arc<AtomicU64>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl EventBus {
     pub fn new() -> Self {
         let (sender, _) = broadcast::channel(100); // Can hold 100 messages
-        Self { sender }
+        Self {
+            sender,
+            retry: None,
+            dropped_events: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    // An event bus that re-sends failed events in the background.
+    // Must be called from inside the Tokio runtime, which runs the retry task.
+    pub fn with_retry(policy: RetryPolicy) -> Self {
+        let mut bus = Self::new();
+        let retry = RetryQueue {
+            policy,
+            pending: Arc::new(Mutex::new(VecDeque::new())),
+            wake: Arc::new(Notify::new()),
+        };
+        tokio::spawn(run_retry_queue(
+            retry.clone(),
+            bus.sender.clone(),
+            Arc::clone(&bus.dropped_events),
+        ));
+        bus.retry = Some(retry);
+        bus
+    }
+
+    // How many events were given up on since the bus was created
+    pub fn dropped_events(&self) -> u64 {
+        self.dropped_events.load(Ordering::Relaxed)
     }
 
     // Send an event to all connected clients
@@ -105,7 +373,7 @@ impl EventBus {
         // Check if there are any active receivers before broadcasting
         let receiver_count = self.sender.receiver_count();
 
-        if receiver_count == 0 {
+        if receiver_count == 0 && self.retry.is_none() {
             // No clients connected, just log the event without broadcasting
             println!("📝 Event occurred but no clients connected: {:?}", event);
             return;
@@ -115,19 +383,314 @@ impl EventBus {
             Ok(_) => {
                 println!("📡 Broadcasting event to {} clients", receiver_count);
             }
-            Err(e) => {
-                // Only log as error if it's not a "no receivers" error
-                if e.to_string().contains("no receivers") {
-                    println!("📝 Event occurred but no clients connected");
-                } else {
-                    eprintln!("❌ Failed to broadcast event: {}", e);
+            Err(broadcast::error::SendError(event)) => match &self.retry {
+                Some(retry) => self.queue_for_retry(retry, event),
+                None => {
+                    eprintln!("❌ Failed to broadcast event: {:?}", event);
+                    self.dropped_events.fetch_add(1, Ordering::Relaxed);
                 }
-            }
+            },
         }
     }
 
+    fn queue_for_retry(&self, retry: &RetryQueue, event: BlockchainEvent) {
+        let mut pending = retry.pending.lock().unwrap();
+        if pending.len() >= retry.policy.capacity {
+            eprintln!("❌ Retry queue full, dropping event: {:?}", event);
+            self.dropped_events.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        println!("🔁 Broadcast failed, will retry: {:?}", event);
+        pending.push_back((event, 0));
+        retry.wake.notify_one();
+    }
+
     // Get a receiver to listen for events
     pub fn subscribe(&self) -> EventReceiver {
         self.sender.subscribe()
     }
 }
+
+// 🎯 What is an Event Log?
+// It's a diary of everything that happened during a run. Every event on the bus
+// is appended to a file as one JSON object per line (the "JSON Lines" format),
+// so a run can be replayed or analyzed later with tools like `jq`.
+
+pub const DEFAULT_EVENT_LOG: &str = "events.jsonl";
+
+// Start a task that appends every event on the bus to `path`.
+// Write errors are reported but never stop the simulator. The task finishes
+// once every sender of the bus has been dropped.
+pub fn spawn_event_log(event_bus: &EventBus, path: impl Into<PathBuf>) -> JoinHandle<()> {
+    // Subscribe right away so no event is missed while the file is being opened
+    let mut receiver = event_bus.subscribe();
+    let path = path.into();
+
+    tokio::spawn(async move {
+        let mut file = match OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await
+        {
+            Ok(file) => file,
+            Err(e) => {
+                eprintln!("❌ Could not open event log {}: {}", path.display(), e);
+                return;
+            }
+        };
+
+        loop {
+            match receiver.recv().await {
+                Ok(event) => {
+                    let mut line = match serde_json::to_string(&event) {
+                        Ok(json) => json,
+                        Err(e) => {
+                            eprintln!("❌ Failed to serialize event: {}", e);
+                            continue;
+                        }
+                    };
+                    line.push('\n');
+                    if let Err(e) = file.write_all(line.as_bytes()).await {
+                        eprintln!("❌ Failed to write to event log: {}", e);
+                    }
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    eprintln!(
+                        "⚠️ Event log fell behind, {} events were not written",
+                        skipped
+                    );
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+
+        if let Err(e) = file.flush().await {
+            eprintln!("❌ Failed to write to event log: {}", e);
+        }
+    })
+}
+
+// The background task behind `EventBus::with_retry`. It sleeps until something
+// is queued, then re-sends the queue every `delay` until it is empty.
+async fn run_retry_queue(retry: RetryQueue, sender: EventSender, dropped_events: Arc<AtomicU64>) {
+    loop {
+        retry.wake.notified().await;
+
+        loop {
+            tokio::time::sleep(retry.policy.delay).await;
+
+            let batch: Vec<_> = retry.pending.lock().unwrap().drain(..).collect();
+            if batch.is_empty() {
+                break;
+            }
+
+            for (event, retries) in batch {
+                match sender.send(event) {
+                    Ok(_) => println!("📡 Delivered event after {} retries", retries + 1),
+                    Err(broadcast::error::SendError(event))
+                        if retries + 1 < retry.policy.max_retries =>
+                    {
+                        retry
+                            .pending
+                            .lock()
+                            .unwrap()
+                            .push_back((event, retries + 1));
+                    }
+                    Err(broadcast::error::SendError(event)) => {
+                        eprintln!(
+                            "❌ Dropping event after {} retries: {:?}",
+                            retry.policy.max_retries, event
+                        );
+                        dropped_events.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BlockChain;
+    use crate::test_support::sample_block;
+
+    fn quick_retry() -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 3,
+            delay: Duration::from_millis(20),
+            capacity: 10,
+        }
+    }
+
+    fn sample_event() -> BlockchainEvent {
+        BlockchainEvent::BlockchainUpdated {
+            total_blocks: 1,
+            total_transactions: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_broadcast_is_delivered_on_retry() {
+        let bus = EventBus::with_retry(quick_retry());
+
+        // Nobody is listening yet, so the first attempt fails
+        bus.broadcast(sample_event());
+        let mut receiver = bus.subscribe();
+
+        let event = tokio::time::timeout(Duration::from_secs(1), receiver.recv())
+            .await
+            .expect("event was not retried")
+            .unwrap();
+        assert!(matches!(
+            event,
+            BlockchainEvent::BlockchainUpdated {
+                total_blocks: 1,
+                ..
+            }
+        ));
+        assert_eq!(bus.dropped_events(), 0);
+    }
+
+    #[tokio::test]
+    async fn persistently_failing_event_is_dropped_after_max_retries() {
+        let bus = EventBus::with_retry(quick_retry());
+        bus.broadcast(sample_event());
+
+        // 3 retries, 20ms apart
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        assert_eq!(bus.dropped_events(), 1);
+        assert!(
+            bus.retry
+                .as_ref()
+                .unwrap()
+                .pending
+                .lock()
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn without_retry_nothing_is_counted_as_dropped() {
+        let bus = EventBus::new();
+        bus.broadcast(sample_event());
+        assert_eq!(bus.dropped_events(), 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn mining_a_block_appends_its_events_to_the_log() {
+        let path =
+            std::env::temp_dir().join(format!("blockchain-events-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let event_bus = EventBus::new();
+        let event_log = spawn_event_log(&event_bus, &path);
+
+        let mut chain = BlockChain::new().unwrap();
+        let mut block = chain.prepare_block(sample_block()).unwrap();
+        block.mine_block_with_visual_hash(chain.hash_algo, &event_bus, "miner");
+        chain.add_new_block(block, &event_bus).unwrap();
+
+        drop(event_bus);
+        event_log.await.unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let events: Vec<serde_json::Value> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0]["BlockMiningStarted"]["block_index"], 1);
+        assert_eq!(
+            events[1]["BlockMined"]["hash"],
+            chain.chain[1].hash.as_str()
+        );
+        assert_eq!(events[2]["BlockchainUpdated"]["total_blocks"], 2);
+    }
+
+    fn mined(block_index: u32) -> BlockchainEvent {
+        BlockchainEvent::BlockMined {
+            block_index,
+            hash: String::new(),
+            miner: "miner".to_string(),
+            timestamp: 0,
+            transactions_count: 0,
+            hash_attempts: 1,
+            mining_millis: 0,
+            hash_rate: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn a_slow_client_loses_its_oldest_events_without_holding_up_others() {
+        let event_bus = EventBus::new();
+        let manager = ConnectionManager::new().with_queue_capacity(3);
+        let forwarder = manager.forward_events(event_bus.subscribe());
+        let slow = manager.add_connection(Uuid::new_v4()).await;
+        let fast = manager.add_connection(Uuid::new_v4()).await;
+
+        for index in 0..5 {
+            event_bus.broadcast(mined(index));
+            let event = tokio::time::timeout(Duration::from_secs(1), fast.next())
+                .await
+                .expect("fast client was held up");
+            assert!(
+                matches!(event, Some(BlockchainEvent::BlockMined { block_index, .. }) if block_index == index)
+            );
+        }
+
+        // The slow client never read anything and kept only the newest events
+        drop(event_bus);
+        forwarder.await.unwrap();
+        let mut kept = Vec::new();
+        while let Some(BlockchainEvent::BlockMined { block_index, .. }) = slow.next().await {
+            kept.push(block_index);
+        }
+        assert_eq!(kept, vec![2, 3, 4]);
+        assert_eq!(manager.dropped_events(), 2);
+        assert_eq!(fast.next().await.map(|event| event.name()), None);
+    }
+
+    #[tokio::test]
+    async fn queues_keep_only_the_latest_totals_and_respect_the_filter() {
+        let queue = ClientQueue::new(10, Arc::new(AtomicU64::new(0)));
+        queue.push(sample_event());
+        queue.push(mined(1));
+        queue.push(BlockchainEvent::BlockchainUpdated {
+            total_blocks: 2,
+            total_transactions: 0,
+        });
+        assert_eq!(queue.len(), 2);
+
+        queue.set_filter(Some(HashSet::from(["BlockMined"])));
+        queue.push(sample_event());
+        queue.push(mined(2));
+        queue.close();
+
+        let mut events = Vec::new();
+        while let Some(event) = queue.next().await {
+            events.push(event);
+        }
+        assert_eq!(events.len(), 3);
+        assert!(matches!(
+            events[0],
+            BlockchainEvent::BlockMined { block_index: 1, .. }
+        ));
+        assert!(matches!(
+            events[1],
+            BlockchainEvent::BlockchainUpdated {
+                total_blocks: 2,
+                ..
+            }
+        ));
+        assert!(matches!(
+            events[2],
+            BlockchainEvent::BlockMined { block_index: 2, .. }
+        ));
+    }
+}
@@ -0,0 +1,60 @@
+use warp::Filter;
+
+// 🎯 What is a Block Explorer?
+// A web page for browsing the chain. The latest blocks show up as they are mined
+// (over the WebSocket feed), and clicking a block, a transaction or an address
+// shows its details from the REST API. The page is compiled into the binary
+// with `include_str!`, so there is nothing extra to install or serve.
+
+const INDEX_HTML: &str = include_str!("../explorer/index.html");
+const EXPLORER_JS: &str = include_str!("../explorer/explorer.js");
+
+// GET / - The explorer page
+// GET /explorer.js - Its script
+// The page has to know which port the WebSocket server listens on, so that is
+// filled in before it is served.
+pub fn routes(
+    ws_port: u16,
+) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    let index_html = INDEX_HTML.replace("{{WS_PORT}}", &ws_port.to_string());
+    let index = warp::path::end()
+        .and(warp::get())
+        .map(move || warp::reply::html(index_html.clone()));
+
+    let script = warp::path!("explorer.js").and(warp::get()).map(|| {
+        warp::reply::with_header(
+            EXPLORER_JS,
+            "content-type",
+            "application/javascript; charset=utf-8",
+        )
+    });
+
+    index.or(script)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn page_and_script_are_served_with_the_websocket_port() {
+        let routes = routes(9080);
+
+        let page = warp::test::request().path("/").reply(&routes).await;
+        assert_eq!(page.status(), 200);
+        let html = String::from_utf8_lossy(page.body());
+        assert!(html.contains(r#"<meta name="ws-port" content="9080">"#));
+        assert!(html.contains(r#"<script src="/explorer.js">"#));
+
+        let script = warp::test::request()
+            .path("/explorer.js")
+            .reply(&routes)
+            .await;
+        assert_eq!(script.status(), 200);
+        assert_eq!(
+            script.headers()["content-type"],
+            "application/javascript; charset=utf-8"
+        );
+        assert!(!script.body().is_empty());
+    }
+}
@@ -0,0 +1,266 @@
+use crate::{Block, BlockChain, BlockchainError, DifficultyConfig, HashAlgo, RewardSchedule};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+// 🎯 Exporting a Chain
+// An export file starts with a small header (what the file is, which format
+// version, and the chain settings) followed by the blocks one at a time.
+// Writing and reading block by block means a long chain never has to be turned
+// into one giant string. Every block is checked as it is read, so a damaged or
+// edited file is caught at the exact block that is wrong.
+//
+// JSON files have one object per line (header first). CBOR files are a plain
+// sequence of CBOR items: smaller and faster, but not human readable.
+
+const EXPORT_MAGIC: &str = "blockchain-sim";
+pub const EXPORT_VERSION: u32 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Cbor,
+}
+
+impl Format {
+    // `.cbor` files are CBOR, anything else is JSON
+    pub fn from_path(path: impl AsRef<Path>) -> Format {
+        match path.as_ref().extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("cbor") => Format::Cbor,
+            _ => Format::Json,
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(Format::Json),
+            "cbor" => Ok(Format::Cbor),
+            other => Err(format!("Unknown export format: {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ExportHeader {
+    magic: String,
+    version: u32,
+    hash_algo: HashAlgo,
+    max_tx_per_block: usize,
+    difficulty: DifficultyConfig,
+    #[serde(default = "RewardSchedule::legacy")]
+    rewards: RewardSchedule,
+    block_count: u64,
+}
+
+fn export_error(context: &str, e: impl std::fmt::Display) -> BlockchainError {
+    BlockchainError::StorageError(format!("{} : {}", context, e))
+}
+
+fn write_item<T: Serialize>(
+    writer: &mut impl Write,
+    item: &T,
+    format: Format,
+) -> Result<(), BlockchainError> {
+    match format {
+        Format::Json => serde_json::to_writer(&mut *writer, item)
+            .map_err(|e| export_error("Serialize Error", e))
+            .and_then(|_| {
+                writer
+                    .write_all(b"\n")
+                    .map_err(|e| export_error("Write Error", e))
+            }),
+        Format::Cbor => {
+            ciborium::into_writer(item, writer).map_err(|e| export_error("Serialize Error", e))
+        }
+    }
+}
+
+fn read_item<T: DeserializeOwned>(
+    reader: &mut impl BufRead,
+    format: Format,
+) -> Result<T, BlockchainError> {
+    match format {
+        Format::Json => {
+            let mut line = String::new();
+            match reader.read_line(&mut line) {
+                Ok(0) => Err(BlockchainError::StorageError(
+                    "Parse Error : the export ended early".to_string(),
+                )),
+                Ok(_) => serde_json::from_str(&line).map_err(|e| export_error("Parse Error", e)),
+                Err(e) => Err(export_error("Read Error", e)),
+            }
+        }
+        Format::Cbor => ciborium::from_reader(reader).map_err(|e| export_error("Parse Error", e)),
+    }
+}
+
+impl BlockChain {
+    // Write the chain to `path`, one block at a time
+    pub fn export(&self, path: impl AsRef<Path>, format: Format) -> Result<(), BlockchainError> {
+        let file = File::create(path).map_err(|e| export_error("Write Error", e))?;
+        let mut writer = BufWriter::new(file);
+
+        let header = ExportHeader {
+            magic: EXPORT_MAGIC.to_string(),
+            version: EXPORT_VERSION,
+            hash_algo: self.hash_algo,
+            max_tx_per_block: self.max_tx_per_block,
+            difficulty: self.difficulty,
+            rewards: self.rewards,
+            block_count: self.chain.len() as u64,
+        };
+        write_item(&mut writer, &header, format)?;
+        for block in &self.chain {
+            write_item(&mut writer, block, format)?;
+        }
+        writer.flush().map_err(|e| export_error("Write Error", e))
+    }
+
+    // Read a chain written by `export`, verifying every block as it arrives
+    pub fn import(path: impl AsRef<Path>, format: Format) -> Result<BlockChain, BlockchainError> {
+        let file = File::open(path).map_err(|e| export_error("Read Error", e))?;
+        Self::import_from(&mut BufReader::new(file), format)
+    }
+
+    fn import_from(
+        reader: &mut impl BufRead,
+        format: Format,
+    ) -> Result<BlockChain, BlockchainError> {
+        let header: ExportHeader = read_item(reader, format)?;
+        if header.magic != EXPORT_MAGIC {
+            return Err(BlockchainError::StorageError(
+                "Parse Error : not a blockchain export".to_string(),
+            ));
+        }
+        if header.version != EXPORT_VERSION {
+            return Err(BlockchainError::StorageError(format!(
+                "Unsupported export version {} (expected {})",
+                header.version, EXPORT_VERSION
+            )));
+        }
+
+        // The genesis block has nothing to link to, only its own hash to match
+        let genesis: Block = read_item(reader, format)?;
+        let mut chain = BlockChain::from_blocks(
+            vec![genesis],
+            header.hash_algo,
+            header.max_tx_per_block,
+            header.difficulty,
+            header.rewards,
+        );
+        if !chain.is_valid() {
+            return Err(BlockchainError::InvalidHash { index: 0 });
+        }
+
+        for expected_index in 1..header.block_count {
+            let block: Block = read_item(reader, format)?;
+            if u64::from(block.index) != expected_index {
+                return Err(BlockchainError::StorageError(format!(
+                    "Parse Error : expected block {} but found block {}",
+                    expected_index, block.index
+                )));
+            }
+            let changes = chain.check_next_block(&block)?;
+            chain.push_block(block, changes);
+        }
+
+        // Nothing may follow the last block
+        let mut rest = [0u8; 1];
+        match reader.read(&mut rest) {
+            Ok(0) => Ok(chain),
+            Ok(_) => Err(BlockchainError::StorageError(
+                "Parse Error : unexpected data after the last block".to_string(),
+            )),
+            Err(e) => Err(export_error("Read Error", e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{address, mine_into, sample_block};
+
+    fn sample_chain() -> BlockChain {
+        let mut chain = BlockChain::new()
+            .unwrap()
+            .with_genesis_allocations(&[(address("alice"), 100)]);
+        for index in 1..=2 {
+            let mut block = sample_block();
+            block.index = index;
+            mine_into(&mut chain, block).unwrap();
+        }
+        chain
+    }
+
+    // Tests run in parallel, so each one exports under its own `name`
+    fn export_to_bytes(chain: &BlockChain, format: Format, name: &str) -> Vec<u8> {
+        let path = std::env::temp_dir().join(format!(
+            "chain-export-{}-{:?}-{}",
+            name,
+            format,
+            std::process::id()
+        ));
+        chain.export(&path, format).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn json_and_cbor_exports_round_trip() {
+        let chain = sample_chain();
+        for format in [Format::Json, Format::Cbor] {
+            let bytes = export_to_bytes(&chain, format, "round-trip");
+            let imported = BlockChain::import_from(&mut bytes.as_slice(), format).unwrap();
+
+            assert_eq!(imported.get_total_block(), 3);
+            assert_eq!(imported.chain[2].hash, chain.chain[2].hash);
+            assert!(imported.is_valid());
+            let tx_hash = chain.chain[1].data.transaction_table[0].hash();
+            assert!(imported.receipt(&tx_hash).is_some());
+        }
+
+        let bytes = export_to_bytes(&chain, Format::Json, "lines");
+        assert_eq!(String::from_utf8(bytes).unwrap().lines().count(), 4);
+    }
+
+    #[test]
+    fn import_rejects_edited_blocks_and_unknown_versions() {
+        let bytes = export_to_bytes(&sample_chain(), Format::Json, "rejects");
+        let text = String::from_utf8(bytes).unwrap();
+
+        let edited = text.replacen("\"amount\":10,", "\"amount\":99,", 1);
+        assert!(matches!(
+            BlockChain::import_from(&mut edited.as_bytes(), Format::Json),
+            Err(BlockchainError::InvalidHash { index: 1 })
+        ));
+
+        let future = text.replacen("\"version\":1", "\"version\":2", 1);
+        assert!(matches!(
+            BlockChain::import_from(&mut future.as_bytes(), Format::Json),
+            Err(BlockchainError::StorageError(msg)) if msg.contains("version 2")
+        ));
+
+        let truncated: String = text
+            .lines()
+            .take(2)
+            .map(|line| format!("{}\n", line))
+            .collect();
+        assert!(BlockChain::import_from(&mut truncated.as_bytes(), Format::Json).is_err());
+    }
+
+    #[test]
+    fn format_comes_from_the_name_or_extension() {
+        assert_eq!("CBOR".parse::<Format>().unwrap(), Format::Cbor);
+        assert!("xml".parse::<Format>().is_err());
+        assert_eq!(Format::from_path("chain.cbor"), Format::Cbor);
+        assert_eq!(Format::from_path("chain.jsonl"), Format::Json);
+    }
+}
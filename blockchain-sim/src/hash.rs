@@ -0,0 +1,81 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sha3::Sha3_256;
+use std::str::FromStr;
+
+// Which hash function is used to seal blocks. The choice is stored on the
+// chain so validation always re-hashes with the same function that mined it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HashAlgo {
+    #[default]
+    Sha256,
+    Sha3_256,
+    Blake3,
+}
+
+impl HashAlgo {
+    pub fn digest(&self, data: &[u8]) -> String {
+        match self {
+            HashAlgo::Sha256 => format!("{:x}", Sha256::digest(data)),
+            HashAlgo::Sha3_256 => format!("{:x}", Sha3_256::digest(data)),
+            HashAlgo::Blake3 => blake3::hash(data).to_hex().to_string(),
+        }
+    }
+
+    // Start a hasher that has already absorbed `prefix`
+    pub(crate) fn prefix_hasher(&self, prefix: &[u8]) -> PrefixHasher {
+        match self {
+            HashAlgo::Sha256 => PrefixHasher::Sha256(Sha256::new_with_prefix(prefix)),
+            HashAlgo::Sha3_256 => PrefixHasher::Sha3_256(Sha3_256::new_with_prefix(prefix)),
+            HashAlgo::Blake3 => {
+                let mut hasher = blake3::Hasher::new();
+                hasher.update(prefix);
+                PrefixHasher::Blake3(hasher)
+            }
+        }
+    }
+}
+
+// A hasher state with the constant part of a block already fed in. Mining clones
+// it for every nonce instead of re-hashing the whole block from scratch.
+// Only one exists per block being mined, so the large BLAKE3 state is kept inline
+// rather than boxed (boxing would add an allocation to every clone).
+#[allow(clippy::large_enum_variant)]
+pub(crate) enum PrefixHasher {
+    Sha256(Sha256),
+    Sha3_256(Sha3_256),
+    Blake3(blake3::Hasher),
+}
+
+impl PrefixHasher {
+    pub(crate) fn hash_with_nonce(&self, nonce: u64) -> String {
+        let nonce = nonce.to_string();
+        match self {
+            PrefixHasher::Sha256(hasher) => {
+                format!("{:x}", hasher.clone().chain_update(nonce).finalize())
+            }
+            PrefixHasher::Sha3_256(hasher) => {
+                format!("{:x}", hasher.clone().chain_update(nonce).finalize())
+            }
+            PrefixHasher::Blake3(hasher) => hasher
+                .clone()
+                .update(nonce.as_bytes())
+                .finalize()
+                .to_hex()
+                .to_string(),
+        }
+    }
+}
+
+impl FromStr for HashAlgo {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "sha256" | "sha-256" => Ok(HashAlgo::Sha256),
+            "sha3" | "sha3-256" => Ok(HashAlgo::Sha3_256),
+            "blake3" => Ok(HashAlgo::Blake3),
+            other => Err(format!("Unknown hash algorithm: {}", other)),
+        }
+    }
+}
@@ -0,0 +1,280 @@
+use crate::{Address, Block, BlockChain, BlockchainError, Transaction};
+use std::collections::HashMap;
+
+// 🎯 What is a Ledger?
+// The chain only stores transactions. To know how many coins an address owns we
+// replay every transaction from the genesis block onwards: coinbase transactions
+// mint new coins, everything else moves coins from one address to another.
+// A sender can never spend more than it has, so no balance ever goes negative.
+// The chain does that replay once when it is loaded, then applies each new
+// block to its ledger as it is appended.
+//
+// 🎯 Replay protection
+// Every address also has a nonce: the number of transactions it has sent. A
+// transaction has to carry exactly that number, so once it is mined the same
+// signed transaction can never be applied again, not even in the same block.
+
+#[derive(Clone, Debug, Default)]
+pub struct Ledger {
+    balances: HashMap<Address, u64>,
+    // Address -> nonce its next transaction has to use
+    nonces: HashMap<Address, u64>,
+}
+
+impl Ledger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Replay the whole chain. Fails if any block spends coins its sender didn't have.
+    pub fn from_chain(blockchain: &BlockChain) -> Result<Ledger, BlockchainError> {
+        let mut ledger = Ledger::new();
+        for block in &blockchain.chain {
+            ledger.apply_block(block)?;
+        }
+        Ok(ledger)
+    }
+
+    // Unknown addresses simply have nothing
+    pub fn balance(&self, address: &Address) -> u64 {
+        self.balances.get(address).copied().unwrap_or(0)
+    }
+
+    // What the next transaction from `address` has to use as its nonce
+    pub fn nonce(&self, address: &Address) -> u64 {
+        self.nonces.get(address).copied().unwrap_or(0)
+    }
+
+    // Coins held across all addresses, i.e. everything in circulation
+    pub fn total_supply(&self) -> Result<u64, BlockchainError> {
+        self.balances.values().try_fold(0u64, |total, balance| {
+            total
+                .checked_add(*balance)
+                .ok_or_else(|| BlockchainError::Overflow("the total supply".to_string()))
+        })
+    }
+
+    // All or nothing: if one transaction of the block fails, none of them apply
+    pub fn apply_block(&mut self, block: &Block) -> Result<(), BlockchainError> {
+        let changes = self.block_changes(block)?;
+        self.commit(changes);
+        Ok(())
+    }
+
+    // What `block` would change, worked out without changing this ledger: a
+    // ledger holding only the addresses the block touches. Hand it to `commit`
+    // to apply it. This is how a new block is checked against the chain's
+    // ledger without copying the whole thing.
+    pub fn block_changes(&self, block: &Block) -> Result<Ledger, BlockchainError> {
+        let mut changes = Ledger::new();
+        for transaction in &block.data.transaction_table {
+            self.stage(&mut changes, transaction)?;
+        }
+        Ok(changes)
+    }
+
+    pub fn commit(&mut self, changes: Ledger) {
+        self.balances.extend(changes.balances);
+        self.nonces.extend(changes.nonces);
+    }
+
+    // The sender pays amount + fee, the recipient gets the amount.
+    // The fee goes to the miner through the block's coinbase.
+    // Nothing changes if the sender can't afford it or the nonce is not its next one.
+    pub fn apply_transaction(&mut self, transaction: &Transaction) -> Result<(), BlockchainError> {
+        let mut changes = Ledger::new();
+        self.stage(&mut changes, transaction)?;
+        self.commit(changes);
+        Ok(())
+    }
+
+    // Record what `transaction` does in `changes`, which holds everything
+    // already staged on top of this ledger
+    pub fn stage(
+        &self,
+        changes: &mut Ledger,
+        transaction: &Transaction,
+    ) -> Result<(), BlockchainError> {
+        let balance = |changes: &Ledger, address: &Address| {
+            changes
+                .balances
+                .get(address)
+                .copied()
+                .unwrap_or_else(|| self.balance(address))
+        };
+
+        if !transaction.is_coinbase() {
+            let expected = changes
+                .nonces
+                .get(&transaction.from)
+                .copied()
+                .unwrap_or_else(|| self.nonce(&transaction.from));
+            if transaction.nonce != expected {
+                return Err(BlockchainError::InvalidNonce {
+                    address: transaction.from,
+                    expected,
+                    got: transaction.nonce,
+                });
+            }
+            let cost = transaction.total_cost()?;
+            let balance = balance(changes, &transaction.from);
+            let remaining =
+                balance
+                    .checked_sub(cost)
+                    .ok_or(BlockchainError::InsufficientFunds {
+                        address: transaction.from,
+                        balance,
+                        needed: cost,
+                    })?;
+            changes.balances.insert(transaction.from, remaining);
+            changes.nonces.insert(transaction.from, expected + 1);
+        }
+
+        let received = balance(changes, &transaction.to)
+            .checked_add(transaction.amount)
+            .ok_or_else(|| {
+                BlockchainError::Overflow(format!("the balance of {}", transaction.to))
+            })?;
+        changes.balances.insert(transaction.to, received);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::address;
+
+    #[test]
+    fn coinbase_funds_a_transfer_and_overdrafts_are_rejected() {
+        let mut ledger = Ledger::new();
+        ledger
+            .apply_transaction(&Transaction::coinbase(&address("alice"), 100, 0))
+            .unwrap();
+
+        let transfer = Transaction {
+            from: address("alice"),
+            to: address("bob"),
+            amount: 60,
+            fee: 5,
+            nonce: 0,
+            signature: None,
+            public_key: None,
+        };
+        ledger.apply_transaction(&transfer).unwrap();
+        assert_eq!(ledger.balance(&address("alice")), 35);
+        assert_eq!(ledger.balance(&address("bob")), 60);
+
+        // 60 + 5 is more than the 35 alice has left, so nothing moves
+        let again = Transaction {
+            nonce: 1,
+            ..transfer
+        };
+        assert!(matches!(
+            ledger.apply_transaction(&again),
+            Err(BlockchainError::InsufficientFunds {
+                balance: 35,
+                needed: 65,
+                ..
+            })
+        ));
+        assert_eq!(ledger.balance(&address("alice")), 35);
+        assert_eq!(ledger.balance(&address("bob")), 60);
+        assert_eq!(ledger.balance(&address("carol")), 0);
+        assert_eq!(ledger.nonce(&address("alice")), 1);
+    }
+
+    #[test]
+    fn replayed_and_out_of_order_nonces_are_rejected() {
+        let mut ledger = Ledger::new();
+        ledger
+            .apply_transaction(&Transaction::coinbase(&address("alice"), 100, 0))
+            .unwrap();
+        let transfer = |nonce| Transaction {
+            from: address("alice"),
+            to: address("bob"),
+            amount: 10,
+            fee: 1,
+            nonce,
+            signature: None,
+            public_key: None,
+        };
+
+        ledger.apply_transaction(&transfer(0)).unwrap();
+        // The same transaction a second time is a replay
+        let replay = ledger.apply_transaction(&transfer(0)).unwrap_err();
+        assert!(matches!(
+            replay,
+            BlockchainError::InvalidNonce {
+                expected: 1,
+                got: 0,
+                ..
+            }
+        ));
+        assert_eq!(
+            replay.to_string(),
+            format!(
+                "Nonce 0 of {} was already used; the next one is 1",
+                address("alice")
+            )
+        );
+        assert!(matches!(
+            ledger.apply_transaction(&transfer(2)),
+            Err(BlockchainError::InvalidNonce {
+                expected: 1,
+                got: 2,
+                ..
+            })
+        ));
+        ledger.apply_transaction(&transfer(1)).unwrap();
+
+        assert_eq!(ledger.balance(&address("alice")), 78);
+        assert_eq!(ledger.nonce(&address("alice")), 2);
+        // Receiving doesn't use up a nonce
+        assert_eq!(ledger.nonce(&address("bob")), 0);
+    }
+
+    #[test]
+    fn balances_are_derived_from_the_chain() {
+        let chain = BlockChain::new()
+            .unwrap()
+            .with_genesis_allocations(&[(address("alice"), 500)]);
+        assert!(chain.is_valid());
+
+        let ledger = Ledger::from_chain(&chain).unwrap();
+        assert_eq!(ledger.balance(&address("alice")), 500);
+        assert_eq!(ledger.total_supply().unwrap(), 500);
+    }
+
+    #[test]
+    fn a_block_with_one_bad_transaction_changes_nothing() {
+        let mut ledger = Ledger::new();
+        ledger
+            .apply_transaction(&Transaction::coinbase(&address("alice"), 100, 0))
+            .unwrap();
+        let transfer = |nonce, amount| Transaction {
+            from: address("alice"),
+            to: address("bob"),
+            amount,
+            fee: 0,
+            nonce,
+            signature: None,
+            public_key: None,
+        };
+        let mut block = crate::test_support::sample_block();
+        block.data.transaction_table = vec![transfer(0, 60), transfer(1, 60)];
+
+        assert!(matches!(
+            ledger.apply_block(&block),
+            Err(BlockchainError::InsufficientFunds { balance: 40, .. })
+        ));
+        assert_eq!(ledger.balance(&address("alice")), 100);
+        assert_eq!(ledger.balance(&address("bob")), 0);
+        assert_eq!(ledger.nonce(&address("alice")), 0);
+
+        block.data.transaction_table.pop();
+        ledger.apply_block(&block).unwrap();
+        assert_eq!(ledger.balance(&address("bob")), 60);
+        assert_eq!(ledger.nonce(&address("alice")), 1);
+    }
+}
@@ -0,0 +1,44 @@
+// 🎯 The blockchain engine
+// Blocks, transactions, mining and validation live here as a library, so the
+// simulator binary, the HTTP/WebSocket API and the tests all share one engine.
+
+mod address;
+mod block;
+mod chain;
+pub mod config;
+pub mod difficulty;
+mod error;
+pub mod events;
+pub mod explorer;
+mod export;
+mod hash;
+mod ledger;
+pub mod mempool;
+pub mod metrics;
+pub mod miner;
+pub mod p2p;
+pub mod reward;
+pub mod storage;
+mod transaction;
+pub mod wallet;
+pub mod websocket;
+
+#[cfg(test)]
+mod test_support;
+
+pub use address::{Address, COINBASE};
+pub use block::{Block, format_timestamp};
+pub use chain::{BlockChain, MAX_TX_PER_BLOCK, mine_and_append};
+pub use config::Config;
+pub use difficulty::DifficultyConfig;
+pub use error::BlockchainError;
+pub use export::{EXPORT_VERSION, Format};
+pub use hash::HashAlgo;
+pub use ledger::Ledger;
+pub use mempool::{Mempool, mine_from_mempool, next_nonce, submit_transaction};
+pub use miner::{Miner, MiningStats};
+pub use reward::RewardSchedule;
+pub use storage::ChainStore;
+pub use transaction::{
+    Direction, HistoryEntry, MultipleTransactions, Transaction, TransactionReceipt,
+};
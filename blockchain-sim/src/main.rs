@@ -1,352 +1,321 @@
-use chrono::NaiveDateTime;
+use blockchain_sim::events::{self, ConnectionManager, EventBus, RetryPolicy};
+use blockchain_sim::explorer;
+use blockchain_sim::metrics::{self, Metrics};
+use blockchain_sim::p2p;
+use blockchain_sim::storage::ChainStore;
+use blockchain_sim::wallet::Wallet;
+use blockchain_sim::{
+    Address, BlockChain, BlockchainError, Config, EXPORT_VERSION, Format, Mempool,
+    format_timestamp, mine_from_mempool, next_nonce, submit_transaction, websocket,
+};
+use clap::{Parser, Subcommand};
 use colored::*;
-use serde::Serialize;
-use sha2::{Digest, Sha256};
-use std::fmt;
-use std::fs::File;
-use std::io::Write;
+use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::Arc;
-use std::thread;
 use std::time::Duration;
 use std::time::{SystemTime, UNIX_EPOCH};
-
-// Import our new modules
-mod events;
-mod websocket;
-
-use events::{BlockchainEvent, ConnectionManager, EventBus};
-
-const DIFFICULTY: u32 = 2;
-
-#[derive(Debug)]
-enum BlockchainError {
-    TimeError(String),
-}
-
-#[derive(Clone, Debug, Serialize)]
-struct Transaction {
-    from: String,
-    to: String,
-    amount: u64,
-    fee: u64,
-    signature: Option<String>,
+use tokio::sync::{Mutex, RwLock, watch};
+use tokio::task::JoinHandle;
+use warp::Filter;
+
+// How long the servers get to close their connections on shutdown
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+
+// The demo participants. Their wallets come from their names, so every command
+// and every node builds the same genesis block and knows the same addresses.
+const TRADER_NAMES: [&str; 9] = [
+    "Shivraj", "jarvihs", "phantom", "metamask", "larry", "harry", "zain", "watson", "anna",
+];
+
+// 🎯 The command line.
+// Every command works on the chain stored in the data directory, so blocks mined
+// by one command are there for the next one. Settings come from blockchain.toml
+// and the environment (see the config module).
+#[derive(Debug, Parser)]
+#[command(
+    name = "blockchain-sim",
+    version,
+    about = "A small blockchain to mine, trade on and watch live"
+)]
+struct Cli {
+    /// Who mines and collects the block rewards
+    #[arg(long, global = true, env = "MINER_NAME")]
+    miner: Option<String>,
+
+    /// Settings file to use instead of ./blockchain.toml
+    #[arg(long, global = true, env = "BLOCKCHAIN_CONFIG")]
+    config: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Command,
 }
 
-#[derive(Clone, Debug, Serialize)]
-struct MultipleTransactions {
-    transaction_table: Vec<Transaction>,
+#[derive(Debug, PartialEq, Subcommand)]
+enum Command {
+    /// Mine blocks that pay the reward to --miner
+    Mine {
+        /// How many blocks to mine
+        #[arg(long, default_value_t = 1)]
+        blocks: u32,
+    },
+    /// Send coins from a demo wallet; the transaction is mined straight away
+    Send {
+        /// Demo wallet name of the sender
+        from: String,
+        /// Demo wallet name or address of the recipient
+        to: String,
+        amount: u64,
+        #[arg(long, default_value_t = 1)]
+        fee: u64,
+    },
+    /// Confirmed balance of a demo wallet name or an address
+    Balance { address: String },
+    /// Look at the stored chain
+    Chain {
+        #[command(subcommand)]
+        command: ChainCommand,
+    },
+    /// Run the WebSocket and HTTP API servers and mine what gets submitted
+    Serve {
+        /// WebSocket port, overriding the configured one
+        #[arg(long)]
+        ws_port: Option<u16>,
+        /// HTTP API port, overriding the configured one
+        #[arg(long)]
+        api_port: Option<u16>,
+        /// Accept other nodes on this address
+        #[arg(long)]
+        listen: Option<String>,
+        /// Dial this node at startup (repeatable)
+        #[arg(long = "peer")]
+        peers: Vec<String>,
+    },
+    /// The scripted walk-through: the demo wallets pass coins around, one block each
+    Demo {
+        /// Save blockchain_data.json compact instead of pretty-printed
+        #[arg(long)]
+        compact: bool,
+    },
 }
 
-#[derive(Debug, Serialize)]
-struct Block {
-    index: u32,
-    prev_hash: String,
-    timestamp: u64,
-    data: MultipleTransactions,
-    nonce: u64,
-    hash: String,
+#[derive(Debug, PartialEq, Subcommand)]
+enum ChainCommand {
+    /// Print every block with running totals and check the chain
+    Show,
+    /// Write a versioned export: JSON lines, or CBOR when the path ends in .cbor
+    Export { path: String },
 }
 
-#[derive(Debug, Serialize)]
-struct BlockChain {
-    chain: Vec<Block>,
-}
-
-impl fmt::Display for Block {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let datetime =
-            NaiveDateTime::from_timestamp_opt(self.timestamp as i64, 0).unwrap_or_default();
-        write!(f, "Block {}: {} at {}", self.index, self.data, datetime)
+impl Cli {
+    // The miner name with surrounding spaces removed; blank counts as missing
+    fn miner_name(&self) -> Option<String> {
+        self.miner
+            .as_deref()
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(String::from)
     }
 }
 
-impl fmt::Display for Transaction {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "From: {} To: {} Amount: {} Fee: {}",
-            self.from, self.to, self.amount, self.fee
-        )
+// Anything as long as an address has to be one, so a typo fails its checksum
+// instead of quietly naming a new demo wallet. Anything shorter is a name.
+fn resolve_address(name_or_address: &str) -> Result<Address, BlockchainError> {
+    if name_or_address.len() >= 25 {
+        Address::parse(name_or_address)
+    } else {
+        Ok(Wallet::from_name(name_or_address).address())
     }
 }
 
-impl fmt::Display for MultipleTransactions {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut result = String::new();
-        for (i, transaction) in self.transaction_table.iter().enumerate() {
-            result.push_str(&format!("Transaction {}: {} ", i + 1, transaction));
-        }
-        write!(f, "{}", result)
+// Open the chain in the configured data directory, or create and store a new one.
+// The chain settings only shape a new chain; a stored one keeps its own.
+fn open_chain(config: &Config) -> Result<BlockChain, BlockchainError> {
+    let store = ChainStore::open(&config.storage.data_dir)?;
+    if let Some(stored) = store.load()? {
+        return Ok(stored);
     }
-}
 
-impl Block {
-    fn new(
-        index: u32,
-        prev_hash: String,
-        data: MultipleTransactions,
-    ) -> Result<Block, BlockchainError> {
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map_err(|e| BlockchainError::TimeError(format!("Time Error : {}", e)))?;
-
-        Ok(Block {
-            index,
-            prev_hash,
-            timestamp: timestamp.as_secs(),
-            data,
-            nonce: 0,
-            hash: String::new(),
+    // 🎯 Every demo wallet starts with some coins from the genesis block,
+    // otherwise nobody could pay for the first transactions
+    let allocations: Vec<(Address, u64)> = TRADER_NAMES
+        .iter()
+        .map(|name| {
+            (
+                Wallet::from_name(name).address(),
+                config.chain.starting_balance,
+            )
         })
-    }
-
-    fn calculate_hash(&self) -> String {
-        let data = format!(
-            "{} {} {} {} {}",
-            self.index, &self.prev_hash, self.timestamp, &self.data, self.nonce
-        );
-        let mut hasher = Sha256::new();
-        hasher.update(data.as_bytes());
-        let result = hasher.finalize();
-        format!("{:x}", result)
-    }
-
-    // 🎯 Updated mining function to broadcast events!
-    fn mine_block_with_visual_hash(&mut self, event_bus: &EventBus, miner: &str) {
-        let mut iteration = 0;
-
-        // Broadcast that mining has started
-        event_bus.broadcast(BlockchainEvent::BlockMiningStarted {
-            block_index: self.index,
-            miner: miner.to_string(),
-            timestamp: self.timestamp,
-        });
-
-        loop {
-            self.hash = self.calculate_hash();
-            iteration += 1;
-            if !self.hash.is_empty() && &self.hash[..DIFFICULTY as usize] == "00" {
-                println!(
-                    "{}",
-                    format!("Block Mined with Hash {} ", self.index).green()
-                );
-
-                // 🎯 Broadcast that block was successfully mined!
-                event_bus.broadcast(BlockchainEvent::BlockMined {
-                    block_index: self.index,
-                    hash: self.hash.clone(),
-                    miner: miner.to_string(),
-                    timestamp: self.timestamp,
-                    transactions_count: self.data.transaction_table.len(),
-                });
-
-                if iteration > 100 {
-                    println!("{}", "Mining is in process ".yellow());
-                    thread::sleep(Duration::from_secs(3));
-                    println!("{}", format!("Mined Hash: {} ", self.hash).cyan());
-                    break;
-                }
-                // Break after successful mining to avoid multiple broadcasts
-                break;
-            }
-            self.nonce += 1;
-        }
-    }
-}
-
-impl BlockChain {
-    fn new() -> Result<BlockChain, BlockchainError> {
-        let genesis_block_data = MultipleTransactions {
-            transaction_table: vec![],
-        };
-        let genesis_block = Block::new(0, String::new(), genesis_block_data)?;
-        Ok(BlockChain {
-            chain: vec![genesis_block],
-        })
-    }
-
-    // 🎯 Updated to broadcast events when adding blocks
-    fn add_new_block(&mut self, mut new_block: Block, event_bus: &EventBus, miner: &str) {
-        let prev_hash = self.chain.last().unwrap().hash.clone();
-        new_block.prev_hash = prev_hash;
-
-        // Mine the block (this will broadcast mining events)
-        new_block.mine_block_with_visual_hash(event_bus, miner);
-
-        // Add the block to the chain
-        self.chain.push(new_block);
-
-        // 🎯 Broadcast that blockchain was updated
-        event_bus.broadcast(BlockchainEvent::BlockchainUpdated {
-            total_blocks: self.chain.len(),
-            total_transactions: self
-                .chain
-                .iter()
-                .map(|b| b.data.transaction_table.len())
-                .sum(),
-        });
-    }
-
-    fn get_total_block(&self) -> usize {
-        self.chain.len()
-    }
+        .collect();
+    BlockChain::with_hash_algo(config.chain.hash_algo)?
+        .with_max_tx_per_block(config.chain.max_tx_per_block)
+        .with_difficulty(config.difficulty())
+        .with_rewards(config.rewards())
+        .with_genesis_allocations(&allocations)
+        .with_store(store)
 }
 
-// 🎯 New function to create transactions (without broadcasting individual events)
-fn create_transaction(
-    from: &str,
-    to: &str,
-    amount: u64,
-    fee: u64,
-    _block_index: u32,
-    _event_bus: &EventBus, // Keep parameter for future use but don't broadcast here
-) -> Transaction {
-    let transaction = Transaction {
-        from: from.to_string(),
-        to: to.to_string(),
-        amount,
-        fee,
-        signature: None,
-    };
-
-    // Note: We'll broadcast all transactions together when the block is mined
-    // This reduces spam and makes the events more meaningful
-
-    transaction
-}
-
-#[tokio::main]
-async fn main() {
+fn print_settings(chain: &BlockChain) {
     println!(
         "{}",
-        "Welcome to Blockchain Simulator with WebSocket!"
-            .blue()
-            .bold()
+        format!(
+            "Chain of {} blocks, hashed with {:?}",
+            chain.get_total_block(),
+            chain.hash_algo
+        )
+        .cyan()
     );
-
-    println!("{}", "Enter the Miner Name: ".yellow());
-    let mut miner_name = String::new();
-    std::io::stdin().read_line(&mut miner_name).unwrap();
-    miner_name = miner_name.trim().to_string();
-
     println!(
         "{}",
-        "Starting the Blockchain Simulation with Real-time Updates".green()
+        format!(
+            "Difficulty: {} bits, retargeting every {} blocks",
+            chain.difficulty.initial_bits, chain.difficulty.retarget_interval
+        )
+        .cyan()
     );
+    println!(
+        "{}",
+        format!("Mining on {} threads", chain.miner.threads()).cyan()
+    );
+}
 
-    // 🎯 Initialize our event system
-    let event_bus = EventBus::new();
-    let connection_manager = Arc::new(ConnectionManager::new());
-
-    // Create a shared blockchain that can be accessed by multiple threads
-    let blockchain = Arc::new(tokio::sync::RwLock::new(match BlockChain::new() {
-        Ok(chain) => chain,
-        Err(e) => {
-            println!("{}", format!("Error Creating Blockchain : {:?}", e).red());
-            return;
-        }
-    }));
-
+// Start the WebSocket and HTTP API servers in background tasks. Both stop once
+// `shutdown` turns true; the returned handles finish when they have.
+async fn start_servers(
+    blockchain: &Arc<RwLock<BlockChain>>,
+    mempool: &Arc<Mutex<Mempool>>,
+    event_bus: &EventBus,
+    connection_manager: &Arc<ConnectionManager>,
+    ws_addr: SocketAddr,
+    api_addr: SocketAddr,
+    shutdown: &watch::Receiver<bool>,
+) -> [JoinHandle<()>; 2] {
     // 🎯 Start the WebSocket server in a separate task
+    let ws_blockchain = Arc::clone(blockchain);
     let ws_event_bus = event_bus.clone();
-    let ws_connection_manager = Arc::clone(&connection_manager);
-    tokio::spawn(async move {
-        let ws_server = websocket::WebSocketServer::new(ws_event_bus, ws_connection_manager);
-        ws_server.start(8080).await;
+    let ws_connection_manager = Arc::clone(connection_manager);
+    let ws_shutdown = shutdown.clone();
+    let ws_server = tokio::spawn(async move {
+        let ws_server =
+            websocket::WebSocketServer::new(ws_blockchain, ws_event_bus, ws_connection_manager);
+        ws_server.start(ws_addr, ws_shutdown).await;
     });
 
+    // 🎯 Count mined blocks for GET /metrics
+    let metrics = Metrics::new();
+    metrics::spawn_recorder(event_bus, metrics.clone());
+
     // 🎯 Start the HTTP API server in a separate task
-    let api_blockchain = Arc::clone(&blockchain);
-    let api_connection_manager = Arc::clone(&connection_manager);
-    tokio::spawn(async move {
-        let routes = websocket::create_api_routes(api_blockchain, api_connection_manager);
-        println!("🌐 Starting HTTP API server on http://127.0.0.1:3000");
-        warp::serve(routes).run(([127, 0, 0, 1], 3000)).await;
+    let api_blockchain = Arc::clone(blockchain);
+    let api_connection_manager = Arc::clone(connection_manager);
+    let api_event_bus = event_bus.clone();
+    let api_mempool = Arc::clone(mempool);
+    let mut api_shutdown = shutdown.clone();
+    let api_server = tokio::spawn(async move {
+        let metrics_routes = metrics::routes(
+            metrics,
+            Arc::clone(&api_blockchain),
+            Arc::clone(&api_connection_manager),
+            Arc::clone(&api_mempool),
+        );
+        let routes = websocket::create_api_routes(
+            api_blockchain,
+            api_connection_manager,
+            api_event_bus,
+            api_mempool,
+            api_shutdown.clone(),
+        )
+        .or(metrics_routes)
+        .or(explorer::routes(ws_addr.port()));
+        println!("🌐 Starting HTTP API server on http://{}", api_addr);
+        let (_, server) = warp::serve(routes).bind_with_graceful_shutdown(api_addr, async move {
+            let _ = api_shutdown.wait_for(|stop| *stop).await;
+        });
+        server.await;
+        println!("🛑 HTTP API server stopped");
     });
 
     // Give the servers a moment to start
     tokio::time::sleep(Duration::from_secs(1)).await;
+    [ws_server, api_server]
+}
 
-    let trader_names = vec![
-        "Shivraj", "jarvihs", "phantom", "metamask", "larry", "harry", "zain", "watson", "anna",
-    ];
+// Mine whatever is pending into the next block and list its transactions
+async fn mine_block(
+    blockchain: &RwLock<BlockChain>,
+    mempool: &Mutex<Mempool>,
+    event_bus: &EventBus,
+    miner_name: &str,
+) -> Result<u32, BlockchainError> {
+    let miner_address = Wallet::from_name(miner_name).address();
+    let block_index =
+        mine_from_mempool(blockchain, mempool, event_bus, miner_name, &miner_address).await?;
+
+    let block = blockchain.read().await.chain[block_index as usize].clone();
+    println!(
+        "{}",
+        format!("Block {} Transactions:", block_index).cyan().bold()
+    );
+    for (idx, transaction) in block.data.transaction_table.iter().enumerate() {
+        println!(
+            "{}",
+            format!("  Transaction {}: {}", idx + 1, transaction).blue()
+        );
+    }
+    println!();
+    Ok(block_index)
+}
 
-    let mut sender = miner_name.clone();
+// 🎯 The scripted walk-through: each demo wallet trades with the next one and
+// every round of trades is mined into its own block
+async fn run_demo(
+    blockchain: &RwLock<BlockChain>,
+    mempool: &Mutex<Mempool>,
+    event_bus: &EventBus,
+    miner_name: &str,
+    pretty_json: bool,
+) {
+    println!(
+        "{}",
+        "Starting the Blockchain Simulation with Real-time Updates".green()
+    );
 
-    for i in 0..trader_names.len() {
+    for (i, sender) in TRADER_NAMES.iter().enumerate() {
         println!("{}", format!("Mining Block: {}", i + 1).yellow());
-        let recipient = if i < trader_names.len() - 1 {
-            trader_names[i + 1].to_string()
-        } else {
-            miner_name.clone()
-        };
-
-        // Create multiple transactions for each block
-        let mut transactions = Vec::new();
-
-        // First transaction
-        let transaction1 =
-            create_transaction(&sender, &recipient, 1000, 10, (i + 1) as u32, &event_bus);
-        transactions.push(transaction1);
-
-        // Second transaction
-        let transaction2 =
-            create_transaction(&recipient, &sender, 2000, 20, (i + 1) as u32, &event_bus);
-        transactions.push(transaction2);
-
-        // Third transaction
-        let transaction3 =
-            create_transaction(&sender, &recipient, 3000, 30, (i + 1) as u32, &event_bus);
-        transactions.push(transaction3);
-
-        let multiple_transactions = MultipleTransactions {
-            transaction_table: transactions.clone(),
-        };
-
-        let new_block = match Block::new((i + 1) as u32, String::new(), multiple_transactions) {
-            Ok(block) => block,
-            Err(e) => {
-                println!("{}", format!("Error creating new block: {:?}", e).red());
-                continue;
+        let recipient = TRADER_NAMES[(i + 1) % TRADER_NAMES.len()];
+        let sender_wallet = Wallet::from_name(sender);
+        let recipient_wallet = Wallet::from_name(recipient);
+
+        // 🎯 The participants submit signed transactions to the mempool, just
+        // like an external client would through POST /api/transactions
+        let submissions = [
+            (&sender_wallet, &recipient_wallet, 1000, 10),
+            (&recipient_wallet, &sender_wallet, 2000, 20),
+            (&sender_wallet, &recipient_wallet, 3000, 30),
+        ];
+        // Senders can only spend what the mined blocks say they own, and each
+        // transaction takes the sender's next nonce
+        for (from, to, amount, fee) in submissions {
+            let nonce = next_nonce(blockchain, mempool, &from.address()).await;
+            let transaction = from.create_signed_transaction(&to.address(), amount, fee, nonce);
+            if let Err(e) = submit_transaction(blockchain, mempool, event_bus, transaction).await {
+                println!("{}", format!("Transaction rejected: {}", e).red());
             }
-        };
-
-        // 🎯 Broadcast all transactions in this block
-        for (_idx, transaction) in transactions.iter().enumerate() {
-            event_bus.broadcast(BlockchainEvent::TransactionCreated {
-                from: transaction.from.clone(),
-                to: transaction.to.clone(),
-                amount: transaction.amount,
-                fee: transaction.fee,
-                block_index: (i + 1) as u32,
-            });
         }
 
-        // 🎯 Add the block to our shared blockchain
-        {
-            let mut blockchain_guard = blockchain.write().await;
-            blockchain_guard.add_new_block(new_block, &event_bus, &miner_name);
-        }
-
-        // Display all transactions in this block
-        println!("{}", format!("Block {} Transactions:", i + 1).cyan().bold());
-        for (idx, transaction) in transactions.iter().enumerate() {
-            println!(
-                "{}",
-                format!("  Transaction {}: {}", idx + 1, transaction).blue()
-            );
+        // 🎯 Mine the pending transactions (highest fee first) into the next block.
+        // The miner is paid the block reward and the fees for it.
+        if let Err(e) = mine_block(blockchain, mempool, event_bus, miner_name).await {
+            println!("{}", format!("Error adding block {}: {}", i + 1, e).red());
         }
-        println!();
-
-        sender = recipient;
-
-        // Small delay to see the real-time updates
-        tokio::time::sleep(Duration::from_secs(2)).await;
     }
 
-    let total_blocks = {
+    let (total_blocks, chain_valid) = {
         let blockchain_guard = blockchain.read().await;
-        blockchain_guard.get_total_block()
+        (
+            blockchain_guard.get_total_block(),
+            blockchain_guard.is_valid(),
+        )
     };
 
     println!(
@@ -358,38 +327,444 @@ async fn main() {
         .green()
     );
 
-    let nexa_per_block = 137;
-    let nexa_traded = nexa_per_block * total_blocks;
-    println!("{}", format!("Total Nexa traded: {}", nexa_traded).yellow());
+    println!("{}", format!("Blockchain valid: {}", chain_valid).green());
+    print!("{}", blockchain.read().await);
+
+    // 🎯 Straight from the blocks: every amount sent between wallets
+    match blockchain.read().await.total_traded() {
+        Ok(total) => println!("{}", format!("Total Nexa traded: {}", total).yellow()),
+        Err(e) => println!("{}", e.to_string().red()),
+    }
+
+    // 🎯 Who owns what after all that trading
+    {
+        let chain = blockchain.read().await;
+        let ledger = chain.ledger();
+        println!("{}", "Balances:".cyan().bold());
+        let mut names: Vec<&str> = TRADER_NAMES.to_vec();
+        if !names.contains(&miner_name) {
+            names.push(miner_name);
+        }
+        names.sort();
+        for name in names {
+            let balance = ledger.balance(&Wallet::from_name(name).address());
+            println!("{}", format!("  {}: {}", name, balance).cyan());
+        }
+        match ledger.total_supply() {
+            Ok(supply) => println!("{}", format!("Nexa in circulation: {}", supply).yellow()),
+            Err(e) => println!("{}", e.to_string().red()),
+        }
+    }
 
     let end_timestamp = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .expect("Time run backwards")
         .as_secs();
-    let end_date = NaiveDateTime::from_timestamp_opt(end_timestamp as i64, 0).unwrap_or_default();
-    println!("{}", format!("Simulation ended at {}", end_date).blue());
     println!(
         "{}",
-        "Congratulations! You have successfully completed setting up the blockchain with WebSocket!"
-            .green()
-            .bold()
+        format!("Simulation ended at {}", format_timestamp(end_timestamp)).blue()
     );
 
-    // Save blockchain to JSON file
-    let blockchain_guard = blockchain.read().await;
-    let json = serde_json::to_string_pretty(&*blockchain_guard).unwrap();
-    let mut file = File::create("blockchain_data.json").unwrap();
-    file.write_all(json.as_bytes()).unwrap();
+    // Save blockchain to JSON file, then read it back to make sure it loads
+    let saved = blockchain
+        .read()
+        .await
+        .save_to_file("blockchain_data.json", pretty_json)
+        .and_then(|_| BlockChain::load_from_file("blockchain_data.json"));
+    match saved {
+        Ok(loaded) => println!(
+            "Blockchain saved to the blockchain_data.json file ({} blocks, valid: {})",
+            loaded.get_total_block(),
+            loaded.is_valid()
+        ),
+        Err(e) => println!("{}", format!("Error saving blockchain : {}", e).red()),
+    }
+}
+
+// 🎯 Run the servers (and optionally join other nodes), then keep mining
+// whatever gets submitted through the API until Ctrl+C. Shutting down stops the
+// miner, closes every connection and makes sure the chain is on disk.
+async fn serve(
+    blockchain: &Arc<RwLock<BlockChain>>,
+    mempool: &Arc<Mutex<Mempool>>,
+    event_bus: &EventBus,
+    config: &Config,
+    miner_name: &str,
+    listen: Option<String>,
+    peers: Vec<String>,
+) {
+    // 🎯 Join the P2P network: other nodes hear about our blocks and transactions,
+    // and we catch up with any peer whose valid chain is longer than ours
+    if listen.is_some() || !peers.is_empty() {
+        let node = p2p::Node::new(
+            Arc::clone(blockchain),
+            Arc::clone(mempool),
+            event_bus.clone(),
+        );
+        if let Some(addr) = &listen {
+            match node.listen(addr.as_str()).await {
+                Ok(addr) => println!("{}", format!("P2P node listening on {}", addr).cyan()),
+                Err(e) => println!("{}", format!("Could not listen on {}: {}", addr, e).red()),
+            }
+        }
+        for peer in &peers {
+            if let Err(e) = node.connect(peer.as_str()).await {
+                println!(
+                    "{}",
+                    format!("Could not connect to peer {}: {}", peer, e).red()
+                );
+            }
+        }
+    }
+
+    // 🎯 Ctrl+C flips the shutdown flag; everything that runs until shutdown watches it.
+    // A block that is being mined is abandoned rather than finished.
+    let (stop, shutdown) = watch::channel(false);
+    let mining_chain = Arc::clone(blockchain);
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            println!("{}", "Shutting down...".yellow());
+            let _ = stop.send(true);
+            mining_chain.read().await.cancel_mining();
+        }
+    });
 
-    println!("{} ", "Blockchain saved to the blockchain_data.json file ");
+    let connection_manager = Arc::new(ConnectionManager::new());
+    let servers = start_servers(
+        blockchain,
+        mempool,
+        event_bus,
+        &connection_manager,
+        config.ws_addr(),
+        config.api_addr(),
+        &shutdown,
+    )
+    .await;
 
     // 🎯 Keep the servers running
-    println!("🌐 WebSocket server running on ws://127.0.0.1:8080");
-    println!("🌐 HTTP API server running on http://127.0.0.1:3000");
-    println!("Press Ctrl+C to stop the servers");
+    println!("🌐 WebSocket server running on ws://{}", config.ws_addr());
+    println!("🌐 HTTP API server running on http://{}", config.api_addr());
+    println!("Press Ctrl+C to stop the servers and save the chain");
 
-    // Keep the main thread alive
+    let block_interval = Duration::from_secs(config.chain.block_interval_secs);
+    let mut miner_shutdown = shutdown.clone();
     loop {
-        tokio::time::sleep(Duration::from_secs(10)).await;
+        tokio::select! {
+            _ = tokio::time::sleep(block_interval) => {}
+            _ = miner_shutdown.wait_for(|stop| *stop) => break,
+        }
+        // Only mine when something was submitted, not a reward-only block every tick
+        if mempool.lock().await.is_empty() {
+            continue;
+        }
+        match mine_block(blockchain, mempool, event_bus, miner_name).await {
+            Ok(block_index) => println!(
+                "{}",
+                format!("Mined block {} from submitted transactions", block_index).green()
+            ),
+            Err(e) => println!(
+                "{}",
+                format!("Error mining pending transactions: {}", e).red()
+            ),
+        }
+    }
+
+    // WebSocket clients get a close frame and /api/blocks followers see their
+    // stream end. A client that still won't let go only gets a few seconds.
+    let stopped = futures_util::future::join_all(servers);
+    if tokio::time::timeout(SHUTDOWN_GRACE, stopped).await.is_err() {
+        println!("{}", "Servers did not stop in time".red());
+    }
+
+    // 🎯 Every block was written as it was mined; make sure it all reached the disk
+    let chain = blockchain.read().await;
+    match chain.flush() {
+        Ok(()) => println!(
+            "{}",
+            format!("Chain flushed to disk ({} blocks)", chain.get_total_block()).green()
+        ),
+        Err(e) => println!("{}", format!("Error flushing the chain: {}", e).red()),
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), String> {
+    let miner_name = cli.miner_name();
+    let needs_miner = |command: &str| {
+        miner_name
+            .clone()
+            .ok_or_else(|| format!("`{}` needs a miner name via --miner or MINER_NAME", command))
+    };
+    let mut config = Config::load(cli.config.as_deref()).map_err(|e| e.to_string())?;
+    let chain = open_chain(&config)
+        .map_err(|e| format!("Error opening the blockchain : {}", e))?
+        .with_miner(config.miner());
+
+    // Read-only commands answer straight from the stored chain
+    match &cli.command {
+        Command::Balance { address } => {
+            let ledger = chain.ledger();
+            let resolved = resolve_address(address).map_err(|e| e.to_string())?;
+            let balance = ledger.balance(&resolved);
+            if resolved.to_string() == *address {
+                println!("{}: {}", resolved, balance);
+            } else {
+                println!("{} ({}): {}", address, resolved, balance);
+            }
+            return Ok(());
+        }
+        Command::Chain {
+            command: ChainCommand::Show,
+        } => {
+            print!("{}", chain);
+            println!("Blockchain valid: {}", chain.is_valid());
+            return Ok(());
+        }
+        Command::Chain {
+            command: ChainCommand::Export { path },
+        } => {
+            // Write the export, then check it reads back
+            let format = Format::from_path(path);
+            let imported = chain
+                .export(path, format)
+                .and_then(|_| BlockChain::import(path, format))
+                .map_err(|e| format!("Error exporting blockchain : {}", e))?;
+            println!(
+                "Exported {} blocks to {} ({:?}, format version {})",
+                imported.get_total_block(),
+                path,
+                format,
+                EXPORT_VERSION
+            );
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    // 🎯 Initialize our event system.
+    // With the servers running, failed broadcasts are retried so a reconnecting
+    // dashboard doesn't miss them.
+    let event_bus = if matches!(cli.command, Command::Serve { .. }) {
+        EventBus::with_retry(RetryPolicy::default())
+    } else {
+        EventBus::new()
+    };
+    // Every event is also appended to the event log (events.jsonl by default)
+    let event_log_path = config.storage.event_log.clone();
+    let event_log = events::spawn_event_log(&event_bus, &event_log_path);
+
+    // 🎯 Pending transactions wait in the mempool until the miner picks them up
+    let blockchain = Arc::new(RwLock::new(chain));
+    let mempool = Arc::new(Mutex::new(Mempool::new()));
+
+    let result = match cli.command {
+        Command::Mine { blocks } => {
+            let miner_name = needs_miner("mine")?;
+            for _ in 0..blocks {
+                mine_block(&blockchain, &mempool, &event_bus, &miner_name)
+                    .await
+                    .map_err(|e| format!("Error mining a block: {}", e))?;
+            }
+            Ok(())
+        }
+        Command::Send {
+            from,
+            to,
+            amount,
+            fee,
+        } => {
+            // Nobody else would mine it, so the sender does unless --miner says otherwise
+            let miner_name = miner_name.unwrap_or_else(|| from.clone());
+            let wallet = Wallet::from_name(&from);
+            let to = resolve_address(&to).map_err(|e| e.to_string())?;
+            let nonce = next_nonce(&blockchain, &mempool, &wallet.address()).await;
+            let transaction = wallet.create_signed_transaction(&to, amount, fee, nonce);
+            let tx_hash = submit_transaction(&blockchain, &mempool, &event_bus, transaction)
+                .await
+                .map_err(|e| format!("Transaction rejected: {}", e))?;
+            println!("{}", format!("Submitted transaction {}", tx_hash).green());
+            mine_block(&blockchain, &mempool, &event_bus, &miner_name)
+                .await
+                .map(|_| ())
+                .map_err(|e| format!("Error mining the transaction: {}", e))
+        }
+        Command::Demo { compact } => {
+            let miner_name = needs_miner("demo")?;
+            print_settings(&*blockchain.read().await);
+            run_demo(&blockchain, &mempool, &event_bus, &miner_name, !compact).await;
+            Ok(())
+        }
+        Command::Serve {
+            ws_port,
+            api_port,
+            listen,
+            peers,
+        } => {
+            let miner_name = needs_miner("serve")?;
+            // Flags win over the file and the environment
+            config.server.ws_port = ws_port.unwrap_or(config.server.ws_port);
+            config.server.api_port = api_port.unwrap_or(config.server.api_port);
+            println!(
+                "{}",
+                "Welcome to Blockchain Simulator with WebSocket!"
+                    .blue()
+                    .bold()
+            );
+            print_settings(&*blockchain.read().await);
+            serve(
+                &blockchain,
+                &mempool,
+                &event_bus,
+                &config,
+                &miner_name,
+                listen,
+                peers,
+            )
+            .await;
+            Ok(())
+        }
+        Command::Balance { .. } | Command::Chain { .. } => unreachable!("answered above"),
+    };
+
+    // Closing the bus lets the event log write out what's left and finish. After
+    // `serve`, background tasks (retries, peers) may still hold the bus, so only
+    // wait a moment; every event has been appended as it came anyway.
+    drop(event_bus);
+    let finished = tokio::time::timeout(Duration::from_secs(1), event_log).await;
+    if !matches!(finished, Ok(Err(_))) {
+        println!("Events written to {}", event_log_path);
+    }
+    result
+}
+
+#[tokio::main]
+async fn main() {
+    if let Err(e) = run(Cli::parse()).await {
+        println!("{}", e.red());
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    fn parse(list: &[&str]) -> Result<Cli, clap::Error> {
+        Cli::try_parse_from(std::iter::once("blockchain-sim").chain(list.iter().copied()))
+    }
+
+    #[test]
+    fn cli_definition_is_consistent() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn send_takes_sender_recipient_and_amount() {
+        let cli = parse(&["send", "anna", "zain", "250"]).unwrap();
+        assert_eq!(
+            cli.command,
+            Command::Send {
+                from: "anna".to_string(),
+                to: "zain".to_string(),
+                amount: 250,
+                fee: 1,
+            }
+        );
+
+        assert!(parse(&["send", "anna", "zain"]).is_err());
+        assert!(parse(&["send", "anna", "zain", "lots"]).is_err());
+    }
+
+    #[test]
+    fn miner_flag_works_anywhere_and_blank_counts_as_missing() {
+        let cli = parse(&["mine", "--blocks", "3", "--miner", " alice "]).unwrap();
+        assert_eq!(cli.command, Command::Mine { blocks: 3 });
+        assert_eq!(cli.miner_name().as_deref(), Some("alice"));
+
+        let cli = parse(&["--miner", "  ", "mine"]).unwrap();
+        assert_eq!(cli.command, Command::Mine { blocks: 1 });
+        assert_eq!(cli.miner_name(), None);
+    }
+
+    #[test]
+    fn serve_ports_are_optional_and_every_peer_is_collected() {
+        let cli = parse(&["serve"]).unwrap();
+        assert_eq!(
+            cli.command,
+            Command::Serve {
+                ws_port: None,
+                api_port: None,
+                listen: None,
+                peers: Vec::new(),
+            }
+        );
+
+        let cli = parse(&[
+            "serve",
+            "--ws-port",
+            "9080",
+            "--api-port",
+            "4000",
+            "--listen",
+            "0.0.0.0:9000",
+            "--peer",
+            "10.0.0.2:9000",
+            "--peer",
+            "10.0.0.3:9000",
+        ])
+        .unwrap();
+        assert_eq!(
+            cli.command,
+            Command::Serve {
+                ws_port: Some(9080),
+                api_port: Some(4000),
+                listen: Some("0.0.0.0:9000".to_string()),
+                peers: vec!["10.0.0.2:9000".to_string(), "10.0.0.3:9000".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn chain_and_balance_commands_are_parsed() {
+        let cli = parse(&["chain", "show"]).unwrap();
+        assert_eq!(
+            cli.command,
+            Command::Chain {
+                command: ChainCommand::Show
+            }
+        );
+        let cli = parse(&["chain", "export", "chain.cbor"]).unwrap();
+        assert_eq!(
+            cli.command,
+            Command::Chain {
+                command: ChainCommand::Export {
+                    path: "chain.cbor".to_string()
+                }
+            }
+        );
+        let cli = parse(&["balance", "anna"]).unwrap();
+        assert_eq!(
+            cli.command,
+            Command::Balance {
+                address: "anna".to_string()
+            }
+        );
+
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["chain"]).is_err());
+        assert!(parse(&["bogus"]).is_err());
+    }
+
+    #[test]
+    fn addresses_pass_through_and_names_become_addresses() {
+        let address = Wallet::from_name("anna").address();
+        let text = address.to_string();
+        assert_eq!(resolve_address(&text).unwrap(), address);
+        assert_eq!(resolve_address("anna").unwrap(), address);
+        assert_ne!(resolve_address("zain").unwrap(), address);
+
+        // A mistyped address is an error, not the name of another wallet
+        let typo = text.replace(&text[1..2], if &text[1..2] == "z" { "y" } else { "z" });
+        assert!(resolve_address(&typo).is_err());
     }
 }
@@ -0,0 +1,632 @@
+use crate::events::{BlockchainEvent, EventBus};
+use crate::{
+    Address, Block, BlockChain, BlockchainError, Ledger, MultipleTransactions, Transaction, wallet,
+};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use tokio::sync::{Mutex, RwLock};
+
+// 🎯 What is a Mempool?
+// The "memory pool" is the waiting room for transactions. Anyone can submit a
+// signed transaction; it waits here until a miner picks it up. Miners get paid
+// the fees, so they pick the transactions with the highest fee first.
+// A sender can queue several transactions, numbered with consecutive nonces;
+// they are always mined in that order.
+
+#[derive(Debug, Default)]
+pub struct Mempool {
+    // In arrival order, so equal fees are mined first come, first served
+    transactions: Vec<Transaction>,
+}
+
+impl Mempool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Check a submitted transaction against the confirmed balances and nonces in
+    // `ledger` and queue it. Returns its hash, which can later be looked up with
+    // `BlockChain::receipt` once it is mined.
+    pub fn submit(
+        &mut self,
+        transaction: Transaction,
+        ledger: &Ledger,
+    ) -> Result<String, BlockchainError> {
+        // Unlike the demo blocks, anything coming from outside has to be signed
+        if !wallet::verify_transaction(&transaction) {
+            return Err(BlockchainError::InvalidSignature {
+                from: transaction.from,
+            });
+        }
+
+        let tx_hash = transaction.hash();
+        if self.transactions.iter().any(|tx| tx.hash() == tx_hash) {
+            return Err(BlockchainError::DuplicateTransaction { tx_hash });
+        }
+
+        // The nonce has to follow the sender's mined and pending transactions.
+        // An older one would replay (or replace) a transaction, a newer one
+        // could never be mined.
+        let expected = self.next_nonce(&transaction.from, ledger);
+        if transaction.nonce != expected {
+            return Err(BlockchainError::InvalidNonce {
+                address: transaction.from,
+                expected,
+                got: transaction.nonce,
+            });
+        }
+        let cost = transaction.total_cost()?;
+
+        // Coins already promised to pending transactions can't be spent twice,
+        // and coins still on their way in don't count until they are mined
+        let balance = ledger.balance(&transaction.from);
+        let needed = self
+            .pending_spend(&transaction.from)?
+            .checked_add(cost)
+            .ok_or_else(|| {
+                BlockchainError::Overflow(format!("the pending spend of {}", transaction.from))
+            })?;
+        if needed > balance {
+            return Err(BlockchainError::InsufficientFunds {
+                address: transaction.from,
+                balance,
+                needed,
+            });
+        }
+
+        self.transactions.push(transaction);
+        Ok(tx_hash)
+    }
+
+    // The nonce the next transaction from `address` needs: one past its mined
+    // transactions in `ledger` and the ones waiting here
+    pub fn next_nonce(&self, address: &Address, ledger: &Ledger) -> u64 {
+        let pending = self
+            .transactions
+            .iter()
+            .filter(|tx| tx.from == *address)
+            .count();
+        ledger.nonce(address) + pending as u64
+    }
+
+    // Total amount + fee of everything `address` has waiting in the pool
+    fn pending_spend(&self, address: &Address) -> Result<u64, BlockchainError> {
+        self.transactions
+            .iter()
+            .filter(|tx| tx.from == *address)
+            .try_fold(0u64, |total, tx| {
+                total.checked_add(tx.total_cost()?).ok_or_else(|| {
+                    BlockchainError::Overflow(format!("the pending spend of {}", address))
+                })
+            })
+    }
+
+    // A pending transaction by its hash
+    pub fn get(&self, tx_hash: &str) -> Option<&Transaction> {
+        self.transactions.iter().find(|tx| tx.hash() == tx_hash)
+    }
+
+    pub fn len(&self) -> usize {
+        self.transactions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transactions.is_empty()
+    }
+
+    // Drop pending transactions that `block` already includes
+    pub fn remove_mined(&mut self, block: &Block) {
+        let mined: Vec<String> = block
+            .data
+            .transaction_table
+            .iter()
+            .map(|tx| tx.hash())
+            .collect();
+        self.transactions.retain(|tx| !mined.contains(&tx.hash()));
+    }
+
+    // Take up to `max` transactions out of the pool, highest fee first. Only the
+    // lowest pending nonce of each sender is up for picking, so a sender's
+    // transactions come out in order even if a later one pays more.
+    pub fn drain_by_fee(&mut self, max: usize) -> Vec<Transaction> {
+        let mut drained = Vec::new();
+        while drained.len() < max {
+            let mut lowest_nonce: HashMap<Address, u64> = HashMap::new();
+            for tx in &self.transactions {
+                let nonce = lowest_nonce.entry(tx.from).or_insert(tx.nonce);
+                *nonce = (*nonce).min(tx.nonce);
+            }
+            // Equal fees go first come, first served
+            let next = self
+                .transactions
+                .iter()
+                .enumerate()
+                .filter(|(_, tx)| lowest_nonce[&tx.from] == tx.nonce)
+                .min_by_key(|(position, tx)| (Reverse(tx.fee), *position))
+                .map(|(position, _)| position);
+            let Some(next) = next else {
+                break;
+            };
+            drained.push(self.transactions.remove(next));
+        }
+        drained
+    }
+}
+
+// Check a transaction against the current balances and nonces, queue it and
+// announce it with a `TransactionSubmitted` event, or a `TransactionRejected`
+// one if it can't go in. Everything that accepts transactions (the demo, the
+// HTTP API and other nodes) goes through here.
+pub async fn submit_transaction(
+    blockchain: &RwLock<BlockChain>,
+    mempool: &Mutex<Mempool>,
+    event_bus: &EventBus,
+    transaction: Transaction,
+) -> Result<String, BlockchainError> {
+    let tx_hash = transaction.hash();
+    let queued = async {
+        let chain = blockchain.read().await;
+        // Already mined: accepting it again would spend the coins twice
+        if chain.receipt(&tx_hash).is_some() {
+            return Err(BlockchainError::DuplicateTransaction {
+                tx_hash: tx_hash.clone(),
+            });
+        }
+        // The chain's lock is always taken before the mempool's, here like everywhere else
+        mempool
+            .lock()
+            .await
+            .submit(transaction.clone(), chain.ledger())
+    }
+    .await;
+
+    match &queued {
+        Ok(_) => event_bus.broadcast(BlockchainEvent::TransactionSubmitted { transaction }),
+        Err(e) => reject(event_bus, &transaction, e),
+    }
+    queued
+}
+
+// The nonce to sign the next transaction from `address` with
+pub async fn next_nonce(
+    blockchain: &RwLock<BlockChain>,
+    mempool: &Mutex<Mempool>,
+    address: &Address,
+) -> u64 {
+    let chain = blockchain.read().await;
+    mempool.lock().await.next_nonce(address, chain.ledger())
+}
+
+fn reject(event_bus: &EventBus, transaction: &Transaction, reason: &BlockchainError) {
+    event_bus.broadcast(BlockchainEvent::TransactionRejected {
+        tx_hash: transaction.hash(),
+        from: transaction.from,
+        reason: reason.to_string(),
+    });
+}
+
+// Mine the best pending transactions into the next block, paying the block
+// reward and their fees to `reward_address`. With an empty pool the block only
+// holds the reward.
+// Returns the index of the new block. If the block can't be added, its
+// transactions go back into the pool.
+pub async fn mine_from_mempool(
+    blockchain: &RwLock<BlockChain>,
+    mempool: &Mutex<Mempool>,
+    event_bus: &EventBus,
+    miner: &str,
+    reward_address: &Address,
+) -> Result<u32, BlockchainError> {
+    let (index, max_tx) = {
+        let chain = blockchain.read().await;
+        (chain.get_total_block() as u32, chain.max_tx_per_block)
+    };
+
+    // The reward takes up one of the block's transaction slots
+    let drained = mempool.lock().await.drain_by_fee(max_tx.saturating_sub(1));
+
+    // 🎯 The chain may have moved on since they were submitted (e.g. a block
+    // from another node spent the same nonce), so each one is checked against
+    // the ledger again. Whatever no longer applies is dropped for good.
+    let mut transactions = Vec::with_capacity(drained.len());
+    {
+        let chain = blockchain.read().await;
+        let mut staged = Ledger::new();
+        for transaction in drained {
+            match chain.ledger().stage(&mut staged, &transaction) {
+                Ok(()) => transactions.push(transaction),
+                Err(e) => {
+                    println!("Dropping transaction {}: {}", transaction.hash(), e);
+                    reject(event_bus, &transaction, &e);
+                }
+            }
+        }
+    }
+
+    // 🎯 Broadcast all transactions going into this block
+    for transaction in &transactions {
+        event_bus.broadcast(BlockchainEvent::TransactionCreated {
+            from: transaction.from,
+            to: transaction.to,
+            amount: transaction.amount,
+            fee: transaction.fee,
+            block_index: index,
+        });
+    }
+
+    // 🎯 The coinbase pays the block reward plus every fee in the block
+    let block = blockchain
+        .read()
+        .await
+        .coinbase_amount(index, &transactions)
+        .and_then(|amount| {
+            let data = MultipleTransactions {
+                transaction_table: std::iter::once(Transaction::coinbase(
+                    reward_address,
+                    amount,
+                    index,
+                ))
+                .chain(transactions.iter().cloned())
+                .collect(),
+            };
+            Block::new(index, String::new(), data)
+        });
+    let mined = match block {
+        Ok(block) => crate::mine_and_append(blockchain, block, event_bus, miner).await,
+        Err(e) => Err(e),
+    };
+
+    match mined {
+        Ok(()) => Ok(index),
+        Err(e) => {
+            // Already validated and paid for once, so they go straight back in
+            // unless someone resubmitted them while we were mining
+            let mut mempool = mempool.lock().await;
+            for transaction in transactions {
+                let tx_hash = transaction.hash();
+                if !mempool.transactions.iter().any(|tx| tx.hash() == tx_hash) {
+                    mempool.transactions.push(transaction);
+                }
+            }
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reward::INITIAL_BLOCK_REWARD;
+    use crate::test_support::address;
+    use crate::wallet::Wallet;
+
+    // A ledger in which `wallet` owns `balance` coins
+    fn funded(wallet: &Wallet, balance: u64) -> Ledger {
+        let mut ledger = Ledger::new();
+        ledger
+            .apply_transaction(&Transaction::coinbase(&wallet.address(), balance, 0))
+            .unwrap();
+        ledger
+    }
+
+    #[test]
+    fn submit_rejects_unsigned_forged_and_duplicate_transactions() {
+        let mut mempool = Mempool::new();
+        let wallet = Wallet::generate();
+        let ledger = funded(&wallet, 10_000);
+
+        let transaction = wallet.create_signed_transaction(&address("bob"), 10, 1, 0);
+        let tx_hash = mempool.submit(transaction.clone(), &ledger).unwrap();
+        assert_eq!(tx_hash, transaction.hash());
+
+        assert!(matches!(
+            mempool.submit(transaction.clone(), &ledger),
+            Err(BlockchainError::DuplicateTransaction { .. })
+        ));
+
+        let mut forged = transaction.clone();
+        forged.amount = 1_000;
+        assert!(matches!(
+            mempool.submit(forged, &ledger),
+            Err(BlockchainError::InvalidSignature { .. })
+        ));
+
+        let mut unsigned = transaction;
+        unsigned.signature = None;
+        assert!(mempool.submit(unsigned, &ledger).is_err());
+        assert_eq!(mempool.len(), 1);
+    }
+
+    #[test]
+    fn nonces_must_follow_the_mined_and_pending_transactions() {
+        let mut mempool = Mempool::new();
+        let wallet = Wallet::generate();
+        let mut ledger = funded(&wallet, 10_000);
+        // One transaction of this wallet is already mined
+        ledger
+            .apply_transaction(&wallet.create_signed_transaction(&address("bob"), 1, 1, 0))
+            .unwrap();
+        assert_eq!(mempool.next_nonce(&wallet.address(), &ledger), 1);
+
+        // Replaying the mined one, or signing it again with the same nonce
+        assert!(matches!(
+            mempool.submit(
+                wallet.create_signed_transaction(&address("bob"), 1, 1, 0),
+                &ledger
+            ),
+            Err(BlockchainError::InvalidNonce {
+                expected: 1,
+                got: 0,
+                ..
+            })
+        ));
+        // Skipping a nonce
+        assert!(matches!(
+            mempool.submit(
+                wallet.create_signed_transaction(&address("bob"), 5, 1, 2),
+                &ledger
+            ),
+            Err(BlockchainError::InvalidNonce {
+                expected: 1,
+                got: 2,
+                ..
+            })
+        ));
+
+        mempool
+            .submit(
+                wallet.create_signed_transaction(&address("bob"), 5, 1, 1),
+                &ledger,
+            )
+            .unwrap();
+        // A second transaction with a pending nonce can't replace the first
+        assert!(matches!(
+            mempool.submit(
+                wallet.create_signed_transaction(&address("carol"), 5, 9, 1),
+                &ledger
+            ),
+            Err(BlockchainError::InvalidNonce {
+                expected: 2,
+                got: 1,
+                ..
+            })
+        ));
+        mempool
+            .submit(
+                wallet.create_signed_transaction(&address("bob"), 5, 1, 2),
+                &ledger,
+            )
+            .unwrap();
+        assert_eq!(mempool.next_nonce(&wallet.address(), &ledger), 3);
+        assert_eq!(mempool.len(), 2);
+    }
+
+    #[test]
+    fn drains_highest_fee_first_up_to_the_limit() {
+        let mut mempool = Mempool::new();
+        let wallets: Vec<Wallet> = (0..4).map(|_| Wallet::generate()).collect();
+        let mut ledger = Ledger::new();
+        for wallet in &wallets {
+            ledger
+                .apply_transaction(&Transaction::coinbase(&wallet.address(), 10_000, 0))
+                .unwrap();
+        }
+        for (wallet, (amount, fee)) in wallets.iter().zip([(1, 5), (2, 50), (3, 5), (4, 20)]) {
+            mempool
+                .submit(
+                    wallet.create_signed_transaction(&address("bob"), amount, fee, 0),
+                    &ledger,
+                )
+                .unwrap();
+        }
+
+        let drained = mempool.drain_by_fee(3);
+        let order: Vec<(u64, u64)> = drained.iter().map(|tx| (tx.amount, tx.fee)).collect();
+        assert_eq!(order, vec![(2, 50), (4, 20), (1, 5)]);
+
+        assert_eq!(mempool.len(), 1);
+        assert_eq!(mempool.drain_by_fee(10)[0].amount, 3);
+        assert!(mempool.is_empty());
+    }
+
+    #[test]
+    fn a_senders_transactions_are_drained_in_nonce_order() {
+        let mut mempool = Mempool::new();
+        let (alice, bob) = (Wallet::generate(), Wallet::generate());
+        let mut ledger = funded(&alice, 10_000);
+        ledger
+            .apply_transaction(&Transaction::coinbase(&bob.address(), 10_000, 1))
+            .unwrap();
+        for transaction in [
+            alice.create_signed_transaction(&address("carol"), 1, 1, 0),
+            alice.create_signed_transaction(&address("carol"), 2, 50, 1),
+            bob.create_signed_transaction(&address("carol"), 3, 20, 0),
+        ] {
+            mempool.submit(transaction, &ledger).unwrap();
+        }
+
+        // Alice's big fee has to wait for her first transaction
+        let drained = mempool.drain_by_fee(10);
+        let order: Vec<u64> = drained.iter().map(|tx| tx.fee).collect();
+        assert_eq!(order, vec![20, 1, 50]);
+    }
+
+    #[test]
+    fn pending_transactions_cannot_overdraw_the_sender() {
+        let mut mempool = Mempool::new();
+        let wallet = Wallet::generate();
+        let ledger = funded(&wallet, 100);
+
+        mempool
+            .submit(
+                wallet.create_signed_transaction(&address("bob"), 60, 5, 0),
+                &ledger,
+            )
+            .unwrap();
+        // 65 is already promised, so another 40 + 1 doesn't fit in 100
+        assert!(matches!(
+            mempool.submit(
+                wallet.create_signed_transaction(&address("bob"), 40, 1, 1),
+                &ledger
+            ),
+            Err(BlockchainError::InsufficientFunds {
+                balance: 100,
+                needed: 106,
+                ..
+            })
+        ));
+        mempool
+            .submit(
+                wallet.create_signed_transaction(&address("bob"), 30, 5, 1),
+                &ledger,
+            )
+            .unwrap();
+
+        // Someone with nothing can't spend anything
+        assert!(matches!(
+            mempool.submit(
+                Wallet::generate().create_signed_transaction(&address("bob"), 1, 0, 0),
+                &ledger
+            ),
+            Err(BlockchainError::InsufficientFunds { .. })
+        ));
+        assert_eq!(mempool.len(), 2);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn miner_drains_the_mempool_into_the_next_block() {
+        // Room for the reward plus two transactions
+        let blockchain = RwLock::new(BlockChain::new().unwrap().with_max_tx_per_block(3));
+        let mempool = Mutex::new(Mempool::new());
+        let event_bus = EventBus::new();
+        let wallet = Wallet::generate();
+
+        // An empty pool still mines a block that pays the miner
+        let index = mine_from_mempool(
+            &blockchain,
+            &mempool,
+            &event_bus,
+            "miner",
+            &wallet.address(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(index, 1);
+
+        let ledger = blockchain.read().await.ledger().clone();
+        assert_eq!(ledger.balance(&wallet.address()), INITIAL_BLOCK_REWARD);
+        for (nonce, fee) in [1, 3, 2].into_iter().enumerate() {
+            mempool
+                .lock()
+                .await
+                .submit(
+                    wallet.create_signed_transaction(&address("bob"), 10, fee, nonce as u64),
+                    &ledger,
+                )
+                .unwrap();
+        }
+        let index = mine_from_mempool(
+            &blockchain,
+            &mempool,
+            &event_bus,
+            "miner",
+            &address("someone"),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(index, 2);
+        let chain = blockchain.read().await;
+        let fees: Vec<u64> = chain.chain[2]
+            .data
+            .transaction_table
+            .iter()
+            .skip(1)
+            .map(|tx| tx.fee)
+            .collect();
+        // One sender, so they go in nonce order
+        assert_eq!(fees, vec![1, 3]);
+        assert!(chain.chain[2].data.transaction_table[0].is_coinbase());
+        assert!(chain.is_valid());
+        assert_eq!(mempool.lock().await.len(), 1);
+
+        let ledger = chain.ledger();
+        assert_eq!(
+            ledger.balance(&wallet.address()),
+            INITIAL_BLOCK_REWARD - 11 - 13
+        );
+        assert_eq!(ledger.balance(&address("bob")), 20);
+        // The second miner collected the fees on top of the reward
+        assert_eq!(
+            ledger.balance(&address("someone")),
+            INITIAL_BLOCK_REWARD + 1 + 3
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn replays_and_stale_transactions_are_rejected_with_an_event() {
+        let wallet = Wallet::generate();
+        let chain = BlockChain::new()
+            .unwrap()
+            .with_genesis_allocations(&[(wallet.address(), 1_000)]);
+        let blockchain = RwLock::new(chain);
+        let mempool = Mutex::new(Mempool::new());
+        let event_bus = EventBus::new();
+        let mut events = event_bus.subscribe();
+
+        let pending = wallet.create_signed_transaction(&address("bob"), 10, 1, 0);
+        submit_transaction(&blockchain, &mempool, &event_bus, pending.clone())
+            .await
+            .unwrap();
+        assert!(matches!(
+            events.recv().await.unwrap(),
+            BlockchainEvent::TransactionSubmitted { .. }
+        ));
+
+        // Another node mines a different transaction with the same nonce first
+        let elsewhere = wallet.create_signed_transaction(&address("carol"), 20, 1, 0);
+        let data = MultipleTransactions {
+            transaction_table: vec![elsewhere.clone()],
+        };
+        crate::mine_and_append(
+            &blockchain,
+            Block::new(1, String::new(), data).unwrap(),
+            &event_bus,
+            "other",
+        )
+        .await
+        .unwrap();
+
+        // Our miner drops the pending one instead of mining a replay
+        let index = mine_from_mempool(
+            &blockchain,
+            &mempool,
+            &event_bus,
+            "miner",
+            &address("miner"),
+        )
+        .await
+        .unwrap();
+        let chain = blockchain.read().await;
+        assert_eq!(chain.chain[index as usize].data.transaction_table.len(), 1);
+        assert_eq!(chain.ledger().balance(&address("bob")), 0);
+        drop(chain);
+        assert!(mempool.lock().await.is_empty());
+
+        // Resubmitting what was mined elsewhere is turned away as well
+        assert!(
+            submit_transaction(&blockchain, &mempool, &event_bus, elsewhere)
+                .await
+                .is_err()
+        );
+
+        let rejected: Vec<String> = std::iter::from_fn(|| events.try_recv().ok())
+            .filter_map(|event| match event {
+                BlockchainEvent::TransactionRejected { tx_hash, .. } => Some(tx_hash),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(rejected.len(), 2);
+        assert_eq!(rejected[0], pending.hash());
+    }
+}
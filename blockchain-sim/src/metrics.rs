@@ -0,0 +1,219 @@
+use crate::events::{BlockchainEvent, ConnectionManager, EventBus};
+use crate::{BlockChain, Mempool};
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, IntCounter, IntGauge, Registry, TextEncoder,
+    exponential_buckets,
+};
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{Mutex, RwLock};
+use tokio::task::JoinHandle;
+use warp::Filter;
+
+// 🎯 What are Metrics?
+// Numbers about the running simulator that a monitoring tool can collect over
+// time: how many blocks were mined, how much work each took, how busy the
+// mempool is. `GET /metrics` lists them in the Prometheus text format, so
+// Prometheus can scrape them and Grafana can draw graphs from them.
+//
+// Counters and histograms are filled from the event bus as blocks are mined;
+// gauges (things that go up and down) are read when /metrics is requested.
+
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Registry,
+    pub blocks_mined: IntCounter,
+    pub hash_attempts: Histogram,
+    pub mining_duration: Histogram,
+    pub hash_rate: IntGauge,
+    pub chain_height: IntGauge,
+    pub websocket_clients: IntGauge,
+    pub mempool_transactions: IntGauge,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let blocks_mined = IntCounter::new(
+            "blockchain_blocks_mined_total",
+            "Blocks mined since the simulator started",
+        )
+        .unwrap();
+        // From 1 hash up to about 4 million, each bucket 4 times the last
+        let hash_attempts = Histogram::with_opts(
+            HistogramOpts::new(
+                "blockchain_hash_attempts_per_block",
+                "Nonces hashed before a block met its difficulty",
+            )
+            .buckets(exponential_buckets(1.0, 4.0, 12).unwrap()),
+        )
+        .unwrap();
+        // From 1 millisecond up to about 4 minutes
+        let mining_duration = Histogram::with_opts(
+            HistogramOpts::new(
+                "blockchain_mining_duration_seconds",
+                "Time spent finding the proof of work of a block",
+            )
+            .buckets(exponential_buckets(0.001, 4.0, 10).unwrap()),
+        )
+        .unwrap();
+        let hash_rate = IntGauge::new(
+            "blockchain_hash_rate",
+            "Hashes per second over all mining threads, for the last mined block",
+        )
+        .unwrap();
+        let chain_height = IntGauge::new("blockchain_chain_height", "Blocks in the chain").unwrap();
+        let websocket_clients = IntGauge::new(
+            "blockchain_websocket_clients",
+            "Connected WebSocket clients",
+        )
+        .unwrap();
+        let mempool_transactions = IntGauge::new(
+            "blockchain_mempool_transactions",
+            "Transactions waiting in the mempool",
+        )
+        .unwrap();
+
+        // The names above are fixed and unique, so registering can't fail
+        let registry = Registry::new();
+        registry.register(Box::new(blocks_mined.clone())).unwrap();
+        registry.register(Box::new(hash_attempts.clone())).unwrap();
+        registry
+            .register(Box::new(mining_duration.clone()))
+            .unwrap();
+        registry.register(Box::new(hash_rate.clone())).unwrap();
+        registry.register(Box::new(chain_height.clone())).unwrap();
+        registry
+            .register(Box::new(websocket_clients.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(mempool_transactions.clone()))
+            .unwrap();
+
+        Self {
+            registry,
+            blocks_mined,
+            hash_attempts,
+            mining_duration,
+            hash_rate,
+            chain_height,
+            websocket_clients,
+            mempool_transactions,
+        }
+    }
+
+    // Count a mined block from its `BlockMined` event
+    pub fn record(&self, event: &BlockchainEvent) {
+        if let BlockchainEvent::BlockMined {
+            hash_attempts,
+            mining_millis,
+            hash_rate,
+            ..
+        } = event
+        {
+            self.hash_rate.set(*hash_rate as i64);
+            self.blocks_mined.inc();
+            self.hash_attempts.observe(*hash_attempts as f64);
+            self.mining_duration.observe(*mining_millis as f64 / 1000.0);
+        }
+    }
+
+    // Everything in the Prometheus text format
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        if let Err(e) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            eprintln!("❌ Failed to encode metrics: {}", e);
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
+
+// Start a task that records every mined block on the bus. Like the event log,
+// it finishes once every sender of the bus has been dropped.
+pub fn spawn_recorder(event_bus: &EventBus, metrics: Metrics) -> JoinHandle<()> {
+    let mut receiver = event_bus.subscribe();
+    tokio::spawn(async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => metrics.record(&event),
+                Err(RecvError::Lagged(skipped)) => {
+                    eprintln!(
+                        "⚠️ Metrics fell behind, {} events were not counted",
+                        skipped
+                    );
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    })
+}
+
+// GET /metrics - Everything above, for Prometheus to scrape
+pub fn routes(
+    metrics: Metrics,
+    blockchain: Arc<RwLock<BlockChain>>,
+    connection_manager: Arc<ConnectionManager>,
+    mempool: Arc<Mutex<Mempool>>,
+) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    warp::path!("metrics").and(warp::get()).then(move || {
+        let metrics = metrics.clone();
+        let blockchain = Arc::clone(&blockchain);
+        let connection_manager = Arc::clone(&connection_manager);
+        let mempool = Arc::clone(&mempool);
+        async move {
+            metrics
+                .chain_height
+                .set(blockchain.read().await.chain.len() as i64);
+            metrics
+                .websocket_clients
+                .set(connection_manager.connection_count().await as i64);
+            metrics
+                .mempool_transactions
+                .set(mempool.lock().await.len() as i64);
+            warp::reply::with_header(metrics.render(), "content-type", prometheus::TEXT_FORMAT)
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::sample_block;
+
+    #[tokio::test]
+    async fn mined_blocks_and_gauges_show_up_on_the_metrics_page() {
+        let event_bus = EventBus::new();
+        let metrics = Metrics::new();
+        let recorder = spawn_recorder(&event_bus, metrics.clone());
+
+        let chain = BlockChain::new().unwrap();
+        let mut block = chain.prepare_block(sample_block()).unwrap();
+        block.mine_block_with_visual_hash(chain.hash_algo, &event_bus, "miner");
+        drop(event_bus);
+        recorder.await.unwrap();
+
+        let mempool = Arc::new(Mutex::new(Mempool::new()));
+        let routes = routes(
+            metrics,
+            Arc::new(RwLock::new(chain)),
+            Arc::new(ConnectionManager::new()),
+            mempool,
+        );
+        let response = warp::test::request().path("/metrics").reply(&routes).await;
+        assert_eq!(response.status(), 200);
+        let page = String::from_utf8_lossy(response.body());
+
+        assert!(page.contains("blockchain_blocks_mined_total 1"));
+        assert!(page.contains("blockchain_hash_attempts_per_block_count 1"));
+        assert!(page.contains("blockchain_mining_duration_seconds_count 1"));
+        assert!(page.contains("# TYPE blockchain_hash_rate gauge"));
+        assert!(page.contains("blockchain_chain_height 1"));
+        assert!(page.contains("blockchain_websocket_clients 0"));
+        assert!(page.contains("blockchain_mempool_transactions 0"));
+    }
+}
@@ -0,0 +1,251 @@
+use crate::difficulty::meets_difficulty;
+use crate::events::{BlockchainEvent, EventBus};
+use crate::{Block, BlockchainError, HashAlgo};
+use colored::*;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// 🎯 Mining on every core
+// Finding a nonce is pure trial and error, so the work splits up nicely: each
+// worker thread gets its own slice of the nonce space (with 4 threads, thread 1
+// starts a quarter of the way up) and hashes its way through it. The first one
+// to find a hash that meets the difficulty raises a flag, and the others stop
+// at their next nonce. A cancel flag, raised on shutdown, stops them all the
+// same way. The workers are plain OS threads, so the async runtime
+// keeps answering requests while a block is being mined.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Miner {
+    threads: usize,
+}
+
+// How much work a block took
+#[derive(Clone, Copy, Debug)]
+pub struct MiningStats {
+    pub hash_attempts: u64,
+    pub elapsed: Duration,
+    pub threads: usize,
+}
+
+impl MiningStats {
+    // Hashes per second, over all threads together
+    pub fn hash_rate(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds > 0.0 {
+            self.hash_attempts as f64 / seconds
+        } else {
+            0.0
+        }
+    }
+}
+
+impl Default for Miner {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl Miner {
+    // 0 threads means one per CPU core
+    pub fn new(threads: usize) -> Self {
+        let threads = match threads {
+            0 => thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n,
+        };
+        Self { threads }
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    // Find a nonce for `block` and set its nonce and hash. This blocks until it
+    // is done, so async code should call it from `spawn_blocking`.
+    pub fn mine(
+        &self,
+        block: &mut Block,
+        algo: HashAlgo,
+        event_bus: &EventBus,
+        miner: &str,
+    ) -> MiningStats {
+        let never_cancelled = AtomicBool::new(false);
+        self.mine_until_cancelled(block, algo, event_bus, miner, &never_cancelled)
+            .expect("only the cancel flag stops the search")
+    }
+
+    // Like `mine`, but gives up as soon as `cancel` turns true. The block is
+    // left unchanged then.
+    pub fn mine_until_cancelled(
+        &self,
+        block: &mut Block,
+        algo: HashAlgo,
+        event_bus: &EventBus,
+        miner: &str,
+        cancel: &AtomicBool,
+    ) -> Result<MiningStats, BlockchainError> {
+        // Broadcast that mining has started
+        event_bus.broadcast(BlockchainEvent::BlockMiningStarted {
+            block_index: block.index,
+            miner: miner.to_string(),
+            timestamp: block.timestamp,
+        });
+
+        let started = Instant::now();
+        let Some((nonce, hash, hash_attempts)) = self.search(block, algo, cancel) else {
+            println!(
+                "{}",
+                format!("Mining block {} cancelled", block.index).yellow()
+            );
+            return Err(BlockchainError::MiningCancelled { index: block.index });
+        };
+        block.nonce = nonce;
+        block.hash = hash;
+        let stats = MiningStats {
+            hash_attempts,
+            elapsed: started.elapsed(),
+            threads: self.threads,
+        };
+
+        println!(
+            "{}",
+            format!(
+                "Block {} mined: {} hashes in {:?} ({:.0} H/s, {} thread{})",
+                block.index,
+                stats.hash_attempts,
+                stats.elapsed,
+                stats.hash_rate(),
+                stats.threads,
+                if stats.threads == 1 { "" } else { "s" }
+            )
+            .green()
+        );
+
+        // 🎯 Broadcast that block was successfully mined!
+        event_bus.broadcast(BlockchainEvent::BlockMined {
+            block_index: block.index,
+            hash: block.hash.clone(),
+            miner: miner.to_string(),
+            timestamp: block.timestamp,
+            transactions_count: block.data.transaction_table.len(),
+            hash_attempts: stats.hash_attempts,
+            mining_millis: stats.elapsed.as_millis() as u64,
+            hash_rate: stats.hash_rate() as u64,
+        });
+        Ok(stats)
+    }
+
+    // Hash on every thread until one finds a nonce that meets the difficulty.
+    // Returns that nonce, its hash and how many nonces were tried in total, or
+    // None if `cancel` turned true first.
+    fn search(
+        &self,
+        block: &Block,
+        algo: HashAlgo,
+        cancel: &AtomicBool,
+    ) -> Option<(u64, String, u64)> {
+        // Hash the constant fields once; each nonce only feeds in itself
+        let prefix_hasher = algo.prefix_hasher(block.hash_prefix().as_bytes());
+        let found = AtomicBool::new(false);
+        let winner = Mutex::new(None);
+
+        let workers = self.threads as u64;
+        let span = u64::MAX / workers;
+        let hash_attempts = thread::scope(|scope| {
+            let handles: Vec<_> = (0..workers)
+                .map(|worker| {
+                    let (prefix_hasher, found, winner) = (&prefix_hasher, &found, &winner);
+                    // The last worker also takes the few nonces the division left over
+                    let first = worker * span;
+                    let last = if worker + 1 == workers {
+                        u64::MAX
+                    } else {
+                        first + span - 1
+                    };
+                    scope.spawn(move || {
+                        let mut attempts = 0u64;
+                        for nonce in first..=last {
+                            if found.load(Ordering::Relaxed) || cancel.load(Ordering::Relaxed) {
+                                break;
+                            }
+                            let hash = prefix_hasher.hash_with_nonce(nonce);
+                            attempts += 1;
+                            if meets_difficulty(&hash, block.difficulty_bits) {
+                                // Two workers can succeed at once; the first to flip the flag wins
+                                if !found.swap(true, Ordering::Relaxed) {
+                                    *winner.lock().unwrap() = Some((nonce, hash));
+                                }
+                                break;
+                            }
+                        }
+                        attempts
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("mining thread panicked"))
+                .sum()
+        });
+
+        // Every worker ran out of nonces or was cancelled
+        let (nonce, hash) = winner.into_inner().unwrap()?;
+        Some((nonce, hash, hash_attempts))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::sample_block;
+
+    #[test]
+    fn every_thread_count_finds_a_nonce_that_meets_the_difficulty() {
+        assert!(Miner::default().threads() >= 1);
+
+        for threads in [1, 3, 8] {
+            let mut block = sample_block();
+            block.difficulty_bits = 10;
+            let miner = Miner::new(threads);
+            let stats = miner.mine(&mut block, HashAlgo::Sha256, &EventBus::new(), "miner");
+
+            assert_eq!(block.hash, block.calculate_hash(HashAlgo::Sha256));
+            assert!(meets_difficulty(&block.hash, 10));
+            assert_eq!(stats.threads, threads);
+            assert!(stats.hash_attempts >= 1);
+            // Each worker starts at the bottom of its own slice
+            let span = u64::MAX / threads as u64;
+            assert!(block.nonce % span < stats.hash_attempts);
+        }
+    }
+
+    #[test]
+    fn raising_the_cancel_flag_stops_the_search() {
+        let mut block = sample_block();
+        // Far more work than the test could ever finish
+        block.difficulty_bits = 60;
+        let cancel = AtomicBool::new(false);
+
+        let mined = thread::scope(|scope| {
+            let mining = scope.spawn(|| {
+                Miner::new(2).mine_until_cancelled(
+                    &mut block,
+                    HashAlgo::Sha256,
+                    &EventBus::new(),
+                    "miner",
+                    &cancel,
+                )
+            });
+            thread::sleep(Duration::from_millis(50));
+            cancel.store(true, Ordering::Relaxed);
+            mining.join().unwrap()
+        });
+
+        assert!(matches!(
+            mined,
+            Err(BlockchainError::MiningCancelled { index: 1 })
+        ));
+        assert!(block.hash.is_empty());
+    }
+}
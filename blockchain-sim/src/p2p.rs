@@ -0,0 +1,380 @@
+use crate::events::{BlockchainEvent, EventBus};
+use crate::{Block, BlockChain, BlockchainError, Mempool, Transaction};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{Mutex, RwLock, mpsc};
+
+// 🎯 What is a P2P Network?
+// Instead of one server holding the chain, every node keeps its own copy and
+// talks directly to other nodes (its peers). When a node mines a block or
+// accepts a transaction it tells its peers ("gossip"), and they pass it on to
+// theirs, so news spreads through the whole network.
+//
+// Nodes can disagree, e.g. after one was offline. The rule that settles it:
+// the longest chain that fully validates wins, and everyone switches to it.
+//
+// On the wire every message is one line of JSON.
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Message {
+    // Sent first on every connection so each side knows if the other is ahead
+    Hello { height: usize },
+    // Please send me your whole chain
+    GetChain,
+    Chain { blocks: Vec<Block> },
+    NewBlock { block: Block },
+    NewTransaction { transaction: Transaction },
+}
+
+type PeerSender = mpsc::UnboundedSender<Message>;
+
+// One simulator instance on the network. Cloning it is cheap; clones share
+// the chain, the mempool and the list of connected peers.
+#[derive(Clone)]
+pub struct Node {
+    blockchain: Arc<RwLock<BlockChain>>,
+    mempool: Arc<Mutex<Mempool>>,
+    event_bus: EventBus,
+    peers: Arc<Mutex<HashMap<SocketAddr, PeerSender>>>,
+}
+
+impl Node {
+    // Creating a node starts gossiping everything that happens on `event_bus`:
+    // blocks appended to the chain and transactions accepted into the mempool
+    pub fn new(
+        blockchain: Arc<RwLock<BlockChain>>,
+        mempool: Arc<Mutex<Mempool>>,
+        event_bus: EventBus,
+    ) -> Node {
+        let node = Node {
+            blockchain,
+            mempool,
+            event_bus,
+            peers: Arc::new(Mutex::new(HashMap::new())),
+        };
+        tokio::spawn(node.clone().gossip());
+        node
+    }
+
+    pub async fn peer_count(&self) -> usize {
+        self.peers.lock().await.len()
+    }
+
+    // Accept peers on `addr` in the background. Returns the address actually
+    // bound, which is handy with port 0.
+    pub async fn listen(&self, addr: impl ToSocketAddrs) -> std::io::Result<SocketAddr> {
+        let listener = TcpListener::bind(addr).await?;
+        let local_addr = listener.local_addr()?;
+
+        let node = self.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, addr)) = listener.accept().await {
+                println!("🤝 Peer connected from {}", addr);
+                tokio::spawn(node.clone().handle_peer(stream, addr));
+            }
+        });
+        Ok(local_addr)
+    }
+
+    // Dial another node. Both sides then say hello and the one that is behind
+    // asks for the other's chain.
+    pub async fn connect(&self, addr: impl ToSocketAddrs) -> std::io::Result<()> {
+        let stream = TcpStream::connect(addr).await?;
+        let addr = stream.peer_addr()?;
+        println!("🤝 Connected to peer {}", addr);
+        tokio::spawn(self.clone().handle_peer(stream, addr));
+        Ok(())
+    }
+
+    async fn send_to_all(&self, message: Message) {
+        for sender in self.peers.lock().await.values() {
+            // A closed channel means the peer is disconnecting; it removes itself
+            let _ = sender.send(message.clone());
+        }
+    }
+
+    // Forward local news to every peer. Blocks are sent from the event bus
+    // rather than from the miner, so blocks received from one peer are passed
+    // on to the others the same way.
+    async fn gossip(self) {
+        let mut events = self.event_bus.subscribe();
+        let mut announced = self.blockchain.read().await.get_total_block();
+
+        loop {
+            match events.recv().await {
+                Ok(BlockchainEvent::BlockchainUpdated { .. }) | Err(RecvError::Lagged(_)) => {
+                    let blocks = self
+                        .blockchain
+                        .read()
+                        .await
+                        .chain
+                        .get(announced..)
+                        .map(|blocks| blocks.to_vec())
+                        .unwrap_or_default();
+                    announced += blocks.len();
+                    for block in blocks {
+                        self.send_to_all(Message::NewBlock { block }).await;
+                    }
+                }
+                Ok(BlockchainEvent::TransactionSubmitted { transaction }) => {
+                    self.send_to_all(Message::NewTransaction { transaction })
+                        .await;
+                }
+                Ok(_) => {}
+                Err(RecvError::Closed) => break,
+            }
+        }
+    }
+
+    async fn handle_peer(self, stream: TcpStream, addr: SocketAddr) {
+        let (reader, mut writer) = stream.into_split();
+
+        // Messages for this peer are queued here and written by their own task,
+        // so a slow peer never blocks gossip to the others
+        let (sender, mut outgoing) = mpsc::unbounded_channel::<Message>();
+        let writer_task = tokio::spawn(async move {
+            while let Some(message) = outgoing.recv().await {
+                let Ok(mut line) = serde_json::to_string(&message) else {
+                    continue;
+                };
+                line.push('\n');
+                if writer.write_all(line.as_bytes()).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        let height = self.blockchain.read().await.get_total_block();
+        let _ = sender.send(Message::Hello { height });
+        self.peers.lock().await.insert(addr, sender.clone());
+
+        let mut lines = BufReader::new(reader).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            match serde_json::from_str::<Message>(&line) {
+                Ok(message) => self.handle_message(message, &sender).await,
+                Err(e) => eprintln!("❌ Bad message from peer {}: {}", addr, e),
+            }
+        }
+
+        println!("👋 Peer {} disconnected", addr);
+        self.peers.lock().await.remove(&addr);
+        writer_task.abort();
+    }
+
+    async fn handle_message(&self, message: Message, reply: &PeerSender) {
+        match message {
+            Message::Hello { height } => {
+                if height > self.blockchain.read().await.get_total_block() {
+                    let _ = reply.send(Message::GetChain);
+                }
+            }
+            Message::GetChain => {
+                let blocks = self.blockchain.read().await.chain.clone();
+                let _ = reply.send(Message::Chain { blocks });
+            }
+            Message::Chain { blocks } => self.adopt_chain(blocks).await,
+            Message::NewBlock { block } => self.receive_block(block, reply).await,
+            Message::NewTransaction { transaction } => {
+                // Already known or not affordable here: either way nothing to pass on
+                let _ = crate::submit_transaction(
+                    &self.blockchain,
+                    &self.mempool,
+                    &self.event_bus,
+                    transaction,
+                )
+                .await;
+            }
+        }
+    }
+
+    async fn receive_block(&self, block: Block, reply: &PeerSender) {
+        let mut chain = self.blockchain.write().await;
+        let height = chain.get_total_block();
+
+        if block.index as usize > height {
+            // We missed some blocks; fetch the peer's chain to catch up
+            let _ = reply.send(Message::GetChain);
+            return;
+        }
+        if block.index as usize != height {
+            // Old news, we already have a block at this height
+            return;
+        }
+
+        match chain.add_new_block(block.clone(), &self.event_bus) {
+            Ok(()) => {
+                drop(chain);
+                println!("📦 Received block {} from a peer", block.index);
+                self.mempool.lock().await.remove_mined(&block);
+            }
+            // It builds on a different tip: the peer is on another branch
+            Err(BlockchainError::StaleBlock { .. }) => {
+                let _ = reply.send(Message::GetChain);
+            }
+            Err(e) => eprintln!("❌ Rejected block {} from a peer: {}", block.index, e),
+        }
+    }
+
+    async fn adopt_chain(&self, blocks: Vec<Block>) {
+        let mut chain = self.blockchain.write().await;
+        match chain.replace_chain(blocks, &self.event_bus) {
+            Ok(true) => {
+                println!(
+                    "🔄 Switched to a longer chain from a peer ({} blocks)",
+                    chain.get_total_block()
+                );
+                let mut mempool = self.mempool.lock().await;
+                for block in &chain.chain {
+                    mempool.remove_mined(block);
+                }
+            }
+            Ok(false) => {}
+            Err(e) => eprintln!("❌ Ignored a chain from a peer: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{address, mine_into, sample_block};
+    use crate::wallet::Wallet;
+    use crate::{mine_and_append, submit_transaction};
+    use std::time::Duration;
+
+    fn start_node(chain: BlockChain) -> (Node, Arc<RwLock<BlockChain>>, Arc<Mutex<Mempool>>) {
+        let blockchain = Arc::new(RwLock::new(chain));
+        let mempool = Arc::new(Mutex::new(Mempool::new()));
+        let node = Node::new(
+            Arc::clone(&blockchain),
+            Arc::clone(&mempool),
+            EventBus::new(),
+        );
+        (node, blockchain, mempool)
+    }
+
+    // Gossip is asynchronous, so tests poll for the outcome with a time limit
+    async fn within_5s(what: &str, wait: impl std::future::Future<Output = ()>) {
+        tokio::time::timeout(Duration::from_secs(5), wait)
+            .await
+            .unwrap_or_else(|_| panic!("timed out waiting for {}", what));
+    }
+
+    async fn pause() {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    async fn mine_next(node: &Node, blockchain: &RwLock<BlockChain>) {
+        let index = blockchain.read().await.get_total_block() as u32;
+        let block = Block::new(index, String::new(), sample_block().data).unwrap();
+        mine_and_append(blockchain, block, &node.event_bus, "miner")
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn new_node_syncs_the_longer_chain_then_follows_gossip() {
+        // Both nodes start from the same genesis block, premine included
+        let chain = BlockChain::new()
+            .unwrap()
+            .with_genesis_allocations(&[(address("alice"), 1_000)]);
+        let (alpha, alpha_chain, _) = start_node(chain.clone());
+        mine_next(&alpha, &alpha_chain).await;
+        let addr = alpha.listen("127.0.0.1:0").await.unwrap();
+
+        // A fresh node only has the genesis block, so it adopts alpha's chain
+        let (beta, beta_chain, _) = start_node(chain);
+        beta.connect(addr).await.unwrap();
+        within_5s("beta to sync", async {
+            while beta_chain.read().await.get_total_block() < 2 {
+                pause().await;
+            }
+        })
+        .await;
+        assert_eq!(
+            beta_chain.read().await.chain[1].hash,
+            alpha_chain.read().await.chain[1].hash
+        );
+
+        // A block mined on alpha afterwards is gossiped to beta
+        mine_next(&alpha, &alpha_chain).await;
+        within_5s("the new block to arrive", async {
+            while beta_chain.read().await.get_total_block() < 3 {
+                pause().await;
+            }
+        })
+        .await;
+        assert!(beta_chain.read().await.is_valid());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn transactions_are_gossiped_to_peers() {
+        let wallet = Wallet::generate();
+        let chain = BlockChain::new()
+            .unwrap()
+            .with_genesis_allocations(&[(wallet.address(), 100)]);
+
+        let (alpha, alpha_chain, alpha_pool) = start_node(chain.clone());
+        let addr = alpha.listen("127.0.0.1:0").await.unwrap();
+        let (beta, _, beta_pool) = start_node(chain);
+        beta.connect(addr).await.unwrap();
+        within_5s("the peers to connect", async {
+            while alpha.peer_count().await < 1 || beta.peer_count().await < 1 {
+                pause().await;
+            }
+        })
+        .await;
+
+        let transaction = wallet.create_signed_transaction(&address("bob"), 10, 1, 0);
+        submit_transaction(&alpha_chain, &alpha_pool, &alpha.event_bus, transaction)
+            .await
+            .unwrap();
+        within_5s("the transaction to arrive", async {
+            while beta_pool.lock().await.is_empty() {
+                pause().await;
+            }
+        })
+        .await;
+        assert_eq!(alpha_pool.lock().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn a_longer_but_invalid_chain_is_ignored() {
+        let (node, blockchain, _) = start_node(BlockChain::new().unwrap());
+        let mut blocks = blockchain.read().await.chain.clone();
+        let mut forged = sample_block();
+        forged.prev_hash = blocks[0].hash.clone();
+        forged.hash = "00".repeat(32);
+        blocks.push(forged);
+
+        node.adopt_chain(blocks).await;
+        assert_eq!(blockchain.read().await.get_total_block(), 1);
+    }
+
+    #[tokio::test]
+    async fn a_longer_chain_from_another_genesis_is_ignored() {
+        let (node, blockchain, _) = start_node(BlockChain::new().unwrap());
+
+        // Valid on its own, but it pays mallory a premine we never agreed to
+        let mut other = BlockChain::new()
+            .unwrap()
+            .with_genesis_allocations(&[(address("mallory"), 1_000_000)]);
+        mine_into(&mut other, sample_block()).unwrap();
+        assert!(other.is_valid());
+
+        node.adopt_chain(other.chain.clone()).await;
+        assert_eq!(blockchain.read().await.get_total_block(), 1);
+        let err = blockchain
+            .write()
+            .await
+            .replace_chain(other.chain, &EventBus::new())
+            .unwrap_err();
+        assert!(err.to_string().contains("different genesis block"));
+    }
+}
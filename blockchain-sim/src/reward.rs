@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+
+// 🎯 Where do new coins come from?
+// Every mined block starts with a coinbase transaction that pays the miner.
+// It pays the block reward (brand new coins) plus the fees of every other
+// transaction in the block. To keep the supply limited, the reward halves every
+// `halving_interval` blocks until it reaches zero and only fees are left.
+
+pub const INITIAL_BLOCK_REWARD: u64 = 50_000;
+pub const DEFAULT_HALVING_INTERVAL: u32 = 100;
+
+// Chosen when the chain is created and stored with it, like the difficulty
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RewardSchedule {
+    pub initial_reward: u64,
+    // Halve the reward every this many blocks; 0 never halves
+    pub halving_interval: u32,
+}
+
+impl Default for RewardSchedule {
+    fn default() -> Self {
+        Self {
+            initial_reward: INITIAL_BLOCK_REWARD,
+            halving_interval: DEFAULT_HALVING_INTERVAL,
+        }
+    }
+}
+
+impl RewardSchedule {
+    // Chains saved before the schedule existed paid a flat reward forever
+    pub(crate) fn legacy() -> Self {
+        Self {
+            initial_reward: INITIAL_BLOCK_REWARD,
+            halving_interval: 0,
+        }
+    }
+
+    // New coins the block at `height` may create
+    pub fn reward_at(&self, height: u32) -> u64 {
+        if self.halving_interval == 0 {
+            return self.initial_reward;
+        }
+        // Each halving is a shift right by one bit; after 64 nothing is left
+        let halvings = height / self.halving_interval;
+        self.initial_reward.checked_shr(halvings).unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reward_halves_every_interval_until_it_runs_out() {
+        let schedule = RewardSchedule {
+            initial_reward: 100,
+            halving_interval: 10,
+        };
+        assert_eq!(schedule.reward_at(1), 100);
+        assert_eq!(schedule.reward_at(9), 100);
+        assert_eq!(schedule.reward_at(10), 50);
+        assert_eq!(schedule.reward_at(25), 25);
+        assert_eq!(schedule.reward_at(70), 0);
+        assert_eq!(schedule.reward_at(u32::MAX), 0);
+
+        assert_eq!(
+            RewardSchedule::legacy().reward_at(u32::MAX),
+            INITIAL_BLOCK_REWARD
+        );
+    }
+}
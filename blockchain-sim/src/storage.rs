@@ -0,0 +1,242 @@
+use crate::{Block, BlockChain, BlockchainError, DifficultyConfig, HashAlgo, RewardSchedule};
+use serde::{Deserialize, Serialize};
+use sled::Transactional;
+use sled::transaction::ConflictableTransactionError;
+use std::path::Path;
+
+// 🎯 Why a Database?
+// Keeping the chain only in memory means every restart starts from scratch.
+// sled is an embedded key-value database (a folder on disk, no server needed).
+// Each block is written under its index as soon as it is mined, so after a
+// restart we read them back in order, check them, and carry on mining.
+
+pub const DEFAULT_DATA_DIR: &str = "blockchain_db";
+
+const SETTINGS_KEY: &str = "settings";
+const BLOCKS_TREE: &str = "blocks";
+
+// The chain-wide settings every block was mined and validated with
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredSettings {
+    hash_algo: HashAlgo,
+    max_tx_per_block: usize,
+    difficulty: DifficultyConfig,
+    #[serde(default = "RewardSchedule::legacy")]
+    rewards: RewardSchedule,
+}
+
+fn storage_error(context: &str, e: impl std::fmt::Display) -> BlockchainError {
+    BlockchainError::StorageError(format!("{} : {}", context, e))
+}
+
+// A handle to the on-disk chain. Cloning it is cheap and shares the same database.
+#[derive(Clone, Debug)]
+pub struct ChainStore {
+    db: sled::Db,
+    blocks: sled::Tree,
+}
+
+impl ChainStore {
+    pub fn open(path: impl AsRef<Path>) -> Result<ChainStore, BlockchainError> {
+        let db = sled::open(path).map_err(|e| storage_error("Open Error", e))?;
+        let blocks = db
+            .open_tree(BLOCKS_TREE)
+            .map_err(|e| storage_error("Open Error", e))?;
+        Ok(ChainStore { db, blocks })
+    }
+
+    // Big-endian keys sort in index order, so iterating gives the chain back in order
+    fn key(index: u32) -> [u8; 4] {
+        index.to_be_bytes()
+    }
+
+    fn settings_bytes(chain: &BlockChain) -> Result<Vec<u8>, BlockchainError> {
+        let settings = StoredSettings {
+            hash_algo: chain.hash_algo,
+            max_tx_per_block: chain.max_tx_per_block,
+            difficulty: chain.difficulty,
+            rewards: chain.rewards,
+        };
+        serde_json::to_vec(&settings).map_err(|e| storage_error("Serialize Error", e))
+    }
+
+    fn block_bytes(block: &Block) -> Result<Vec<u8>, BlockchainError> {
+        serde_json::to_vec(block).map_err(|e| storage_error("Serialize Error", e))
+    }
+
+    pub(crate) fn save_settings(&self, chain: &BlockChain) -> Result<(), BlockchainError> {
+        self.db
+            .insert(SETTINGS_KEY, Self::settings_bytes(chain)?)
+            .map_err(|e| storage_error("Write Error", e))?;
+        Ok(())
+    }
+
+    // Write a block and wait until it is on disk
+    pub(crate) fn append(&self, block: &Block) -> Result<(), BlockchainError> {
+        let bytes = Self::block_bytes(block)?;
+        self.blocks
+            .insert(Self::key(block.index), bytes)
+            .map_err(|e| storage_error("Write Error", e))?;
+        self.flush()
+    }
+
+    // Wait until everything written so far is on disk
+    pub(crate) fn flush(&self) -> Result<(), BlockchainError> {
+        self.db
+            .flush()
+            .map_err(|e| storage_error("Flush Error", e))?;
+        Ok(())
+    }
+
+    // Swap the stored blocks for `chain`, e.g. after adopting a longer chain from a peer.
+    // 🎯 It all happens in one transaction: a crash halfway through leaves the old
+    // chain on disk rather than half of each, and there is only one flush to wait on.
+    pub(crate) fn replace(&self, chain: &BlockChain) -> Result<(), BlockchainError> {
+        let settings = Self::settings_bytes(chain)?;
+        let mut batch = sled::Batch::default();
+        // Blocks the new chain doesn't have anymore; the rest are overwritten below
+        for key in self.blocks.iter().keys() {
+            batch.remove(key.map_err(|e| storage_error("Read Error", e))?);
+        }
+        for block in &chain.chain {
+            batch.insert(&Self::key(block.index), Self::block_bytes(block)?);
+        }
+
+        (&*self.db, &self.blocks)
+            .transaction(|(db, blocks)| {
+                db.insert(SETTINGS_KEY, settings.as_slice())?;
+                blocks.apply_batch(&batch)?;
+                Ok::<_, ConflictableTransactionError>(())
+            })
+            .map_err(|e| storage_error("Write Error", e))?;
+        self.flush()
+    }
+
+    // The stored chain, or `None` if nothing was saved yet.
+    // A chain that doesn't validate is an error rather than something to mine on.
+    pub fn load(&self) -> Result<Option<BlockChain>, BlockchainError> {
+        let Some(settings) = self
+            .db
+            .get(SETTINGS_KEY)
+            .map_err(|e| storage_error("Read Error", e))?
+        else {
+            return Ok(None);
+        };
+        let settings: StoredSettings =
+            serde_json::from_slice(&settings).map_err(|e| storage_error("Parse Error", e))?;
+
+        let blocks = self
+            .blocks
+            .iter()
+            .values()
+            .map(|bytes| {
+                let bytes = bytes.map_err(|e| storage_error("Read Error", e))?;
+                serde_json::from_slice::<Block>(&bytes).map_err(|e| storage_error("Parse Error", e))
+            })
+            .collect::<Result<Vec<Block>, BlockchainError>>()?;
+
+        let chain = BlockChain::from_blocks(
+            blocks,
+            settings.hash_algo,
+            settings.max_tx_per_block,
+            settings.difficulty,
+            settings.rewards,
+        );
+        if !chain.is_valid() {
+            return Err(BlockchainError::StorageError(
+                "The stored chain failed validation".to_string(),
+            ));
+        }
+        Ok(Some(chain.with_existing_store(self.clone())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{mine_into, sample_block};
+
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn mined_blocks_survive_a_restart() {
+        let dir = temp_dir("chain-store-restart");
+        let tx_hash = sample_block().data.transaction_table[0].hash();
+        {
+            let store = ChainStore::open(&dir).unwrap();
+            assert!(store.load().unwrap().is_none());
+
+            let mut chain = BlockChain::new().unwrap().with_store(store).unwrap();
+            mine_into(&mut chain, sample_block()).unwrap();
+        }
+
+        // Reopen and keep mining on top of what was there
+        let store = ChainStore::open(&dir).unwrap();
+        let mut chain = store.load().unwrap().unwrap();
+        assert_eq!(chain.get_total_block(), 2);
+        assert!(chain.receipt(&tx_hash).is_some());
+
+        let mut block = sample_block();
+        block.index = 2;
+        mine_into(&mut chain, block).unwrap();
+        drop(chain);
+
+        let chain = store.load().unwrap().unwrap();
+        assert_eq!(chain.get_total_block(), 3);
+        assert!(chain.is_valid());
+        drop((chain, store));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn replace_swaps_every_block_and_the_settings() {
+        let dir = temp_dir("chain-store-replace");
+        let store = ChainStore::open(&dir).unwrap();
+        let mut chain = BlockChain::new()
+            .unwrap()
+            .with_store(store.clone())
+            .unwrap();
+        mine_into(&mut chain, sample_block()).unwrap();
+        let mut block = sample_block();
+        block.index = 2;
+        mine_into(&mut chain, block).unwrap();
+
+        // Left over blocks of the old chain must not survive the swap
+        let mut other = BlockChain::new().unwrap().with_max_tx_per_block(5);
+        mine_into(&mut other, sample_block()).unwrap();
+        store.replace(&other).unwrap();
+
+        let loaded = store.load().unwrap().unwrap();
+        assert_eq!(loaded.get_total_block(), 2);
+        assert_eq!(loaded.chain[1].hash, other.chain[1].hash);
+        assert_eq!(loaded.max_tx_per_block, 5);
+        drop((chain, loaded, store));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn tampered_store_fails_to_load() {
+        let dir = temp_dir("chain-store-tampered");
+        let store = ChainStore::open(&dir).unwrap();
+        let mut chain = BlockChain::new()
+            .unwrap()
+            .with_store(store.clone())
+            .unwrap();
+        mine_into(&mut chain, sample_block()).unwrap();
+
+        let mut block = chain.chain[1].clone();
+        block.data.transaction_table[0].amount = 1_000_000;
+        store.append(&block).unwrap();
+
+        assert!(matches!(
+            store.load(),
+            Err(BlockchainError::StorageError(_))
+        ));
+        drop((chain, store));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,31 @@
+// Fixtures shared by the unit tests of several modules
+use crate::events::EventBus;
+use crate::wallet::Wallet;
+use crate::{Address, Block, BlockChain, BlockchainError, MultipleTransactions, Transaction};
+
+// The address of the demo wallet called `name`
+pub(crate) fn address(name: &str) -> Address {
+    Wallet::from_name(name).address()
+}
+
+pub(crate) fn sample_block() -> Block {
+    Block {
+        index: 1,
+        prev_hash: "abc".to_string(),
+        timestamp: 1_700_000_000,
+        data: MultipleTransactions {
+            transaction_table: vec![Transaction::coinbase(&address("miner"), 10, 1)],
+        },
+        nonce: 42,
+        hash: String::new(),
+        difficulty_bits: crate::difficulty::DEFAULT_DIFFICULTY_BITS,
+    }
+}
+
+// Prepare, mine and append a block the same way `mine_and_append` does
+pub(crate) fn mine_into(chain: &mut BlockChain, block: Block) -> Result<(), BlockchainError> {
+    let event_bus = EventBus::new();
+    let mut block = chain.prepare_block(block)?;
+    block.mine_block_with_visual_hash(chain.hash_algo, &event_bus, "miner");
+    chain.add_new_block(block, &event_bus)
+}
@@ -0,0 +1,125 @@
+use crate::{Address, BlockchainError, COINBASE};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Transaction {
+    // `COINBASE` for coins minted by a block: they come from nowhere, so no one signs them
+    pub from: Address,
+    pub to: Address,
+    pub amount: u64,
+    pub fee: u64,
+    // How many transactions the sender had already sent: 0 for its first, then
+    // 1, 2, ... Signed along with the rest, so a transaction can't be mined twice.
+    // A coinbase uses the height of its block instead.
+    #[serde(default)]
+    pub nonce: u64,
+    pub signature: Option<String>,
+    pub public_key: Option<String>,
+}
+
+impl Transaction {
+    // New coins paid out to `to` by the block at `height`, e.g. a miner's block reward
+    pub fn coinbase(to: &Address, amount: u64, height: u32) -> Transaction {
+        Transaction {
+            from: COINBASE,
+            to: *to,
+            amount,
+            fee: 0,
+            nonce: height as u64,
+            signature: None,
+            public_key: None,
+        }
+    }
+
+    pub fn is_coinbase(&self) -> bool {
+        self.from == COINBASE
+    }
+
+    // The bytes a wallet signs: everything except the signature itself
+    pub fn signing_payload(&self) -> String {
+        format!(
+            "{} {} {} {} {}",
+            self.from, self.to, self.amount, self.fee, self.nonce
+        )
+    }
+
+    // What the sender pays in total. Checked, so huge values are an error instead
+    // of silently wrapping around in release builds.
+    pub fn total_cost(&self) -> Result<u64, BlockchainError> {
+        self.amount.checked_add(self.fee).ok_or_else(|| {
+            BlockchainError::Overflow(format!("the cost of a transaction from {}", self.from))
+        })
+    }
+
+    // Content-derived id used to look a transaction up after it is mined
+    pub fn hash(&self) -> String {
+        let data = format!(
+            "{} {}",
+            self.signing_payload(),
+            self.signature.as_deref().unwrap_or("")
+        );
+        format!("{:x}", Sha256::digest(data.as_bytes()))
+    }
+}
+
+// Proof that a transaction made it into a mined block
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TransactionReceipt {
+    pub tx_hash: String,
+    pub block_index: u32,
+    pub block_hash: String,
+    pub confirmed: bool,
+}
+
+// One line of an address's history: a mined transaction that moved its coins
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct HistoryEntry {
+    pub tx_hash: String,
+    pub block_index: u32,
+    pub block_hash: String,
+    pub timestamp: u64,
+    pub direction: Direction,
+    // The other side: who got the coins that were sent, or who sent the ones received
+    pub counterparty: Address,
+    pub amount: u64,
+    pub fee: u64,
+    // What the address held once this transaction was applied
+    pub balance: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Sent,
+    Received,
+    // Sent to itself, which only costs the fee
+    #[serde(rename = "self")]
+    ToSelf,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MultipleTransactions {
+    pub transaction_table: Vec<Transaction>,
+}
+
+impl fmt::Display for Transaction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "From: {} To: {} Amount: {} Fee: {} Nonce: {}",
+            self.from, self.to, self.amount, self.fee, self.nonce
+        )
+    }
+}
+
+impl fmt::Display for MultipleTransactions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut result = String::new();
+        for (i, transaction) in self.transaction_table.iter().enumerate() {
+            result.push_str(&format!("Transaction {}: {} ", i + 1, transaction));
+        }
+        write!(f, "{}", result)
+    }
+}
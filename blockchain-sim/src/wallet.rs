@@ -0,0 +1,152 @@
+use crate::{Address, Transaction};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use rand::RngCore;
+use rand::rngs::OsRng;
+use sha2::{Digest, Sha256};
+
+// 🎯 What is a Wallet?
+// A wallet holds a secret key that only its owner knows and the matching public key
+// that everyone can see. The address is a short fingerprint of the public key, and
+// signing a transaction proves the sender really owns the address it spends from.
+
+pub struct Wallet {
+    signing_key: SigningKey,
+}
+
+impl Wallet {
+    // Generate a brand new random keypair
+    pub fn generate() -> Self {
+        let mut secret = [0u8; 32];
+        OsRng.fill_bytes(&mut secret);
+        Self::from_secret_bytes(&secret)
+    }
+
+    // Rebuild a wallet from an existing 32 byte secret key
+    pub fn from_secret_bytes(secret: &[u8; 32]) -> Self {
+        Self {
+            signing_key: SigningKey::from_bytes(secret),
+        }
+    }
+
+    // Demo only: the secret key is just the hash of `name`, so the same name always
+    // gets the same wallet. Anyone who knows the name can spend from it!
+    pub fn from_name(name: &str) -> Self {
+        Self::from_secret_bytes(&Sha256::digest(name.as_bytes()).into())
+    }
+
+    pub fn address(&self) -> Address {
+        Address::from_public_key(&self.signing_key.verifying_key())
+    }
+
+    pub fn public_key_hex(&self) -> String {
+        hex::encode(self.signing_key.verifying_key().to_bytes())
+    }
+
+    pub fn secret_key_hex(&self) -> String {
+        hex::encode(self.signing_key.to_bytes())
+    }
+
+    // Build a transaction from this wallet's address and sign it. `nonce` is the
+    // number of transactions this wallet sent before, see `Mempool::next_nonce`.
+    pub fn create_signed_transaction(
+        &self,
+        to: &Address,
+        amount: u64,
+        fee: u64,
+        nonce: u64,
+    ) -> Transaction {
+        let mut transaction = Transaction {
+            from: self.address(),
+            to: *to,
+            amount,
+            fee,
+            nonce,
+            signature: None,
+            public_key: Some(self.public_key_hex()),
+        };
+        let signature = self
+            .signing_key
+            .sign(transaction.signing_payload().as_bytes());
+        transaction.signature = Some(hex::encode(signature.to_bytes()));
+        transaction
+    }
+}
+
+// Check that a signed transaction was signed by the key its `from` address belongs to
+pub fn verify_transaction(transaction: &Transaction) -> bool {
+    let (Some(signature), Some(public_key)) = (&transaction.signature, &transaction.public_key)
+    else {
+        return false;
+    };
+
+    let Some(public_key) = hex::decode(public_key)
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .and_then(|bytes| VerifyingKey::from_bytes(&bytes).ok())
+    else {
+        return false;
+    };
+    let Some(signature) = hex::decode(signature)
+        .ok()
+        .and_then(|bytes| <[u8; 64]>::try_from(bytes).ok())
+        .map(|bytes| Signature::from_bytes(&bytes))
+    else {
+        return false;
+    };
+
+    Address::from_public_key(&public_key) == transaction.from
+        && public_key
+            .verify(transaction.signing_payload().as_bytes(), &signature)
+            .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::address;
+
+    #[test]
+    fn address_is_deterministic_from_key() {
+        let secret = [7u8; 32];
+        let wallet = Wallet::from_secret_bytes(&secret);
+        let same_wallet = Wallet::from_secret_bytes(&secret);
+
+        assert_eq!(wallet.address(), same_wallet.address());
+        assert_eq!(
+            wallet.address().to_string().parse::<Address>().unwrap(),
+            wallet.address()
+        );
+        assert_ne!(wallet.address(), Wallet::generate().address());
+
+        assert_eq!(
+            Wallet::from_name("alice").address(),
+            Wallet::from_name("alice").address()
+        );
+        assert_ne!(
+            Wallet::from_name("alice").address(),
+            Wallet::from_name("bob").address()
+        );
+    }
+
+    #[test]
+    fn signed_transaction_verifies() {
+        let wallet = Wallet::generate();
+        let transaction = wallet.create_signed_transaction(&address("bob"), 100, 1, 0);
+
+        assert_eq!(transaction.from, wallet.address());
+        assert!(verify_transaction(&transaction));
+    }
+
+    #[test]
+    fn tampered_transaction_fails_verification() {
+        let wallet = Wallet::generate();
+        let mut transaction = wallet.create_signed_transaction(&address("bob"), 100, 1, 0);
+        transaction.amount = 1_000_000;
+        assert!(!verify_transaction(&transaction));
+
+        // The nonce is signed too, so a new one can't be put on an old transaction
+        let mut replayed = wallet.create_signed_transaction(&address("bob"), 100, 1, 0);
+        replayed.nonce = 1;
+        assert!(!verify_transaction(&replayed));
+    }
+}
@@ -1,9 +1,22 @@
-use crate::events::{ConnectionManager, EventBus};
-use futures_util::{SinkExt, StreamExt};
+use crate::events::{BlockchainEvent, ClientQueue, ConnectionManager, EventBus};
+use crate::wallet::Wallet;
+use crate::{Address, Mempool, Transaction};
+use futures_util::{SinkExt, Stream, StreamExt, stream};
+use serde::{Deserialize, Serialize};
 use serde_json::json;
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinSet;
 use tokio_tungstenite::accept_async;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
 use uuid::Uuid;
 use warp::Filter;
 
@@ -13,45 +26,83 @@ use warp::Filter;
 // stay connected and can send messages back and forth in real-time!
 
 pub struct WebSocketServer {
+    blockchain: Arc<tokio::sync::RwLock<crate::BlockChain>>,
     event_bus: EventBus,
     connection_manager: Arc<ConnectionManager>,
 }
 
 impl WebSocketServer {
-    pub fn new(event_bus: EventBus, connection_manager: Arc<ConnectionManager>) -> Self {
+    pub fn new(
+        blockchain: Arc<tokio::sync::RwLock<crate::BlockChain>>,
+        event_bus: EventBus,
+        connection_manager: Arc<ConnectionManager>,
+    ) -> Self {
         Self {
+            blockchain,
             event_bus,
             connection_manager,
         }
     }
 
-    // Start the WebSocket server
-    pub async fn start(&self, port: u16) {
-        let addr = format!("127.0.0.1:{}", port);
+    // Start the WebSocket server. It runs until `shutdown` turns true, then
+    // closes every connection with a close frame before returning.
+    pub async fn start(&self, addr: SocketAddr, shutdown: watch::Receiver<bool>) {
         println!("🚀 Starting WebSocket server on ws://{}", addr);
 
         let listener = TcpListener::bind(&addr).await.expect("Failed to bind");
         println!("✅ WebSocket server listening on ws://{}", addr);
+        self.serve(listener, shutdown).await;
+    }
 
-        while let Ok((stream, addr)) = listener.accept().await {
+    async fn serve(&self, listener: TcpListener, mut shutdown: watch::Receiver<bool>) {
+        // Every connection runs in its own task; the set lets us wait for them on shutdown
+        let mut connections = JoinSet::new();
+        // One task copies the bus into every client's own queue
+        let forwarder = self
+            .connection_manager
+            .forward_events(self.event_bus.subscribe());
+
+        loop {
+            let (stream, addr) = tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok(accepted) => accepted,
+                    Err(_) => break,
+                },
+                // Finished connections are collected so the set doesn't keep growing
+                Some(_) = connections.join_next(), if !connections.is_empty() => continue,
+                _ = stopped(&mut shutdown) => break,
+            };
             println!("📞 New connection from: {}", addr);
 
-            // Clone the event bus and connection manager for this connection
+            // Clone the chain, event bus and connection manager for this connection
+            let blockchain = Arc::clone(&self.blockchain);
             let event_bus = self.event_bus.clone();
             let connection_manager = Arc::clone(&self.connection_manager);
 
             // Handle each connection in a separate task (like a separate thread)
-            tokio::spawn(async move {
-                Self::handle_connection(stream, event_bus, connection_manager).await;
-            });
+            connections.spawn(Self::handle_connection(
+                stream,
+                blockchain,
+                event_bus,
+                connection_manager,
+                shutdown.clone(),
+            ));
         }
+
+        // Stop accepting, then let every client get its close frame
+        drop(listener);
+        while connections.join_next().await.is_some() {}
+        forwarder.abort();
+        println!("🛑 WebSocket server stopped");
     }
 
     // Handle a single WebSocket connection
     async fn handle_connection(
         stream: TcpStream,
+        blockchain: Arc<tokio::sync::RwLock<crate::BlockChain>>,
         event_bus: EventBus,
         connection_manager: Arc<ConnectionManager>,
+        mut shutdown: watch::Receiver<bool>,
     ) {
         // Accept the WebSocket connection
         let ws_stream = match accept_async(stream).await {
@@ -65,32 +116,34 @@ impl WebSocketServer {
         // Generate a unique ID for this connection
         let connection_id = Uuid::new_v4();
 
-        // Add this connection to our manager
-        connection_manager.add_connection(connection_id).await;
+        // Add this connection to our manager, which fills its event queue
+        let queue = connection_manager.add_connection(connection_id).await;
 
         // Split the WebSocket into sender and receiver
         let (mut ws_sender, mut ws_receiver) = ws_stream.split();
 
         // 🎯 What are we doing here?
         // We're creating two tasks that run at the same time:
-        // 1. Task 1: Listen for messages from the client
-        // 2. Task 2: Send blockchain events to the client
+        // 1. Task 1: Listen for commands from the client
+        // 2. Task 2: Send blockchain events and command replies to the client
+        // Only task 2 writes to the socket, so task 1 hands it the commands.
+        let (command_sender, mut commands) = mpsc::channel(16);
 
         // Task 1: Handle incoming messages from the client
-        let client_task = tokio::spawn(async move {
+        let mut client_task = tokio::spawn(async move {
             while let Some(msg) = ws_receiver.next().await {
                 match msg {
-                    Ok(msg) => {
-                        // Handle client messages here
-                        if let tokio_tungstenite::tungstenite::Message::Text(text) = msg {
-                            println!("📨 Received from client {}: {}", connection_id, text);
-
-                            // You can add custom commands here
-                            if text == "ping" {
-                                println!("Pong Pong from client {}", connection_id);
-                            }
+                    Ok(Message::Text(text)) => {
+                        println!("📨 Received from client {}: {}", connection_id, text);
+                        if command_sender
+                            .send(ClientCommand::parse(&text))
+                            .await
+                            .is_err()
+                        {
+                            break;
                         }
                     }
+                    Ok(_) => {}
                     Err(e) => {
                         eprintln!("❌ WebSocket error: {}", e);
                         break;
@@ -99,36 +152,71 @@ impl WebSocketServer {
             }
         });
 
-        // Task 2: Send blockchain events to the client
-        let event_task = tokio::spawn(async move {
-            // Subscribe to blockchain events
-            let mut event_receiver = event_bus.subscribe();
+        // Task 2: Send blockchain events and replies to the client
+        let status_connections = Arc::clone(&connection_manager);
+        let mut event_task = tokio::spawn(async move {
+            let mut subscription = Subscription::default();
 
-            while let Ok(event) = event_receiver.recv().await {
-                // Convert the event to JSON
-                let event_json = match serde_json::to_string(&event) {
-                    Ok(json) => json,
-                    Err(e) => {
-                        eprintln!("❌ Failed to serialize event: {}", e);
-                        continue;
+            loop {
+                let message = tokio::select! {
+                    event = queue.next() => match event {
+                        // Convert the event to JSON
+                        Some(event) => match serde_json::to_string(&event) {
+                            Ok(json) => Some(json),
+                            Err(e) => {
+                                eprintln!("❌ Failed to serialize event: {}", e);
+                                continue;
+                            }
+                        },
+                        None => break,
+                    },
+                    Some(command) = commands.recv() => Some(
+                        answer(
+                            command,
+                            &mut subscription,
+                            &queue,
+                            &blockchain,
+                            &status_connections,
+                            &event_bus,
+                        )
+                        .await,
+                    ),
+                    _ = stopped(&mut shutdown) => None,
+                };
+
+                // 🎯 Say goodbye properly: a close frame tells the client the
+                // server is going away, instead of the connection just dropping
+                let Some(message) = message else {
+                    let close = CloseFrame {
+                        code: CloseCode::Away,
+                        reason: "Server shutting down".into(),
+                    };
+                    if let Err(e) = ws_sender.send(Message::Close(Some(close))).await {
+                        eprintln!("❌ Failed to close connection {}: {}", connection_id, e);
                     }
+                    break;
                 };
 
-                // Send the event to the client
-                if let Err(e) = ws_sender
-                    .send(tokio_tungstenite::tungstenite::Message::Text(event_json))
-                    .await
-                {
+                // Send the message to the client
+                if let Err(e) = ws_sender.send(Message::Text(message)).await {
                     eprintln!("❌ Failed to send event to client: {}", e);
                     break;
                 }
             }
         });
 
-        // Wait for either task to complete
+        // Wait for either task to complete, then stop the other one
         tokio::select! {
-            _ = client_task => println!("👋 Client task ended for {}", connection_id),
-            _ = event_task => println!("📡 Event task ended for {}", connection_id),
+            _ = &mut client_task => {
+                println!("👋 Client task ended for {}", connection_id);
+                event_task.abort();
+            }
+            _ = &mut event_task => {
+                println!("📡 Event task ended for {}", connection_id);
+                // Give the client a moment to answer a close frame
+                let _ = tokio::time::timeout(Duration::from_secs(1), &mut client_task).await;
+                client_task.abort();
+            }
         }
 
         // Clean up when connection ends
@@ -136,6 +224,125 @@ impl WebSocketServer {
     }
 }
 
+// Resolves once shutdown is requested, or when nobody can request it anymore
+async fn stopped(shutdown: &mut watch::Receiver<bool>) {
+    let _ = shutdown.wait_for(|stop| *stop).await;
+}
+
+// 🎯 What can a client say?
+// Besides listening, a client can send commands as JSON text messages:
+//   {"subscribe": ["BlockMined"]}     only receive the listed events
+//   {"unsubscribe": ["BlockMined"]}   stop receiving them
+//   {"get": "status"}                 the same answer as GET /api/status
+//   ping                              answered with pong
+// A new connection receives every event until it subscribes to some.
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ClientCommand {
+    Subscribe(Vec<String>),
+    Unsubscribe(Vec<String>),
+    Get(Query),
+    // Plain text, not JSON
+    #[serde(skip_deserializing)]
+    Ping,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Query {
+    Status,
+}
+
+impl ClientCommand {
+    fn parse(text: &str) -> Result<ClientCommand, String> {
+        if text.trim() == "ping" {
+            return Ok(ClientCommand::Ping);
+        }
+        serde_json::from_str(text).map_err(|e| format!("Unknown command {:?}: {}", text, e))
+    }
+}
+
+// The events one connection wants; `None` means all of them.
+// The connection's queue gets a copy, so it only holds events worth sending.
+#[derive(Debug, Default)]
+struct Subscription(Option<HashSet<&'static str>>);
+
+impl Subscription {
+    // Turn the names a client sent into ours, refusing the ones we don't know
+    fn lookup(names: &[String]) -> Result<Vec<&'static str>, String> {
+        names
+            .iter()
+            .map(|name| {
+                BlockchainEvent::NAMES
+                    .into_iter()
+                    .find(|known| known == name)
+                    .ok_or_else(|| {
+                        format!(
+                            "Unknown event {:?}, expected one of {}",
+                            name,
+                            BlockchainEvent::NAMES.join(", ")
+                        )
+                    })
+            })
+            .collect()
+    }
+
+    // The first subscribe narrows the firehose down to the listed events;
+    // later ones add to it
+    fn subscribe(&mut self, names: &[String]) -> Result<(), String> {
+        let names = Self::lookup(names)?;
+        self.0.get_or_insert_default().extend(names);
+        Ok(())
+    }
+
+    fn unsubscribe(&mut self, names: &[String]) -> Result<(), String> {
+        let names = Self::lookup(names)?;
+        let subscribed = self
+            .0
+            .get_or_insert_with(|| BlockchainEvent::NAMES.into_iter().collect());
+        for name in names {
+            subscribed.remove(name);
+        }
+        Ok(())
+    }
+
+    fn names(&self) -> Vec<&'static str> {
+        BlockchainEvent::NAMES
+            .into_iter()
+            .filter(|name| self.0.as_ref().is_none_or(|names| names.contains(name)))
+            .collect()
+    }
+}
+
+// The reply to one client command, as a text message
+async fn answer(
+    command: Result<ClientCommand, String>,
+    subscription: &mut Subscription,
+    queue: &ClientQueue,
+    blockchain: &tokio::sync::RwLock<crate::BlockChain>,
+    connection_manager: &ConnectionManager,
+    event_bus: &EventBus,
+) -> String {
+    let result = match command {
+        Ok(ClientCommand::Ping) => return "pong".to_string(),
+        Ok(ClientCommand::Get(Query::Status)) => {
+            let status = blockchain_status(blockchain, connection_manager, event_bus).await;
+            return json!({ "status": status }).to_string();
+        }
+        Ok(ClientCommand::Subscribe(names)) => subscription.subscribe(&names),
+        Ok(ClientCommand::Unsubscribe(names)) => subscription.unsubscribe(&names),
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(()) => {
+            queue.set_filter(subscription.0.clone());
+            json!({ "subscribed": subscription.names() }).to_string()
+        }
+        Err(e) => json!({ "error": e }).to_string(),
+    }
+}
+
 // 🎯 What are API Endpoints?
 // API endpoints are like different doors to your house.
 // Each door (endpoint) gives you different information.
@@ -144,11 +351,18 @@ impl WebSocketServer {
 pub fn create_api_routes(
     blockchain: Arc<tokio::sync::RwLock<crate::BlockChain>>,
     connection_manager: Arc<ConnectionManager>,
-) -> impl Filter<Extract = impl warp::Reply> + Clone {
-    // GET /api/blocks - Get all blocks
+    event_bus: EventBus,
+    mempool: Arc<tokio::sync::Mutex<Mempool>>,
+    shutdown: watch::Receiver<bool>,
+) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    // GET /api/blocks - Get all blocks, one page at a time
+    // GET /api/blocks?follow=true - Stream existing and newly mined blocks (SSE)
     let get_blocks = warp::path!("api" / "blocks")
         .and(warp::get())
+        .and(warp::query::<BlocksQuery>())
         .and(with_blockchain(Arc::clone(&blockchain)))
+        .and(with_event_bus(event_bus.clone()))
+        .and(warp::any().map(move || shutdown.clone()))
         .and_then(get_all_blocks);
 
     // GET /api/blocks/{index} - Get a specific block
@@ -162,26 +376,76 @@ pub fn create_api_routes(
         .and(warp::get())
         .and(with_blockchain(Arc::clone(&blockchain)))
         .and(with_connection_manager(Arc::clone(&connection_manager)))
+        .and(with_event_bus(event_bus.clone()))
         .and_then(get_blockchain_status);
 
-    // GET /api/transactions - Get all transactions
+    // GET /api/transactions - Get all transactions, one page at a time
     let get_transactions = warp::path!("api" / "transactions")
         .and(warp::get())
+        .and(warp::query::<TransactionsQuery>())
         .and(with_blockchain(Arc::clone(&blockchain)))
         .and_then(get_all_transactions);
 
+    // POST /api/transactions - Submit a signed transaction to the mempool
+    let submit_transaction = warp::path!("api" / "transactions")
+        .and(warp::post())
+        .and(warp::body::json())
+        .and(with_blockchain(Arc::clone(&blockchain)))
+        .and(with_mempool(Arc::clone(&mempool)))
+        .and(with_event_bus(event_bus.clone()))
+        .and_then(submit_transaction);
+
+    // GET /api/transactions/{tx_hash} - One transaction, mined or pending
+    let get_transaction = warp::path!("api" / "transactions" / String)
+        .and(warp::get())
+        .and(with_blockchain(Arc::clone(&blockchain)))
+        .and(with_mempool(Arc::clone(&mempool)))
+        .and_then(get_transaction);
+
+    // GET /api/addresses/{address}/history - Everything an address sent and received
+    let get_history = warp::path!("api" / "addresses" / String / "history")
+        .and(warp::get())
+        .and(warp::query::<HistoryQuery>())
+        .and(with_blockchain(Arc::clone(&blockchain)))
+        .and_then(get_address_history);
+
+    // GET /api/balances/{address} - Confirmed balance of an address, and the
+    // nonce its next transaction has to be signed with
+    let get_balance = warp::path!("api" / "balances" / String)
+        .and(warp::get())
+        .and(with_blockchain(Arc::clone(&blockchain)))
+        .and(with_mempool(Arc::clone(&mempool)))
+        .and_then(get_balance);
+
     // GET /api/blocks/{index}/transactions - Get transactions for a specific block
     let get_block_transactions = warp::path!("api" / "blocks" / u32 / "transactions")
         .and(warp::get())
         .and(with_blockchain(Arc::clone(&blockchain)))
         .and_then(get_block_transactions);
 
+    // GET /api/tx/{hash} - Get the receipt of a mined transaction
+    let get_receipt = warp::path!("api" / "tx" / String)
+        .and(warp::get())
+        .and(with_blockchain(Arc::clone(&blockchain)))
+        .and_then(get_transaction_receipt);
+
+    // POST /api/wallet - Generate a new demo wallet
+    let create_wallet = warp::path!("api" / "wallet")
+        .and(warp::post())
+        .and_then(create_demo_wallet);
+
     // Combine all routes
     get_blocks
         .or(get_block)
         .or(get_status)
         .or(get_transactions)
+        .or(get_transaction)
+        .or(submit_transaction)
+        .or(get_balance)
         .or(get_block_transactions)
+        .or(get_receipt)
+        .or(get_history)
+        .or(create_wallet)
 }
 
 // Helper function to inject blockchain into route handlers
@@ -201,23 +465,280 @@ fn with_connection_manager(
     warp::any().map(move || Arc::clone(&connection_manager))
 }
 
+// Helper function to inject the mempool into route handlers
+fn with_mempool(
+    mempool: Arc<tokio::sync::Mutex<Mempool>>,
+) -> impl Filter<Extract = (Arc<tokio::sync::Mutex<Mempool>>,), Error = std::convert::Infallible> + Clone
+{
+    warp::any().map(move || Arc::clone(&mempool))
+}
+
+// Helper function to inject the event bus into route handlers
+fn with_event_bus(
+    event_bus: EventBus,
+) -> impl Filter<Extract = (EventBus,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || event_bus.clone())
+}
+
 // API Route Handlers
 
+// Handlers hold the read lock only to look things up or copy what they need;
+// nothing is replayed under it, and it is released before serializing, so a
+// slow response never delays the miner waiting for the write lock.
+
+// 🎯 What is Pagination?
+// After a long run the chain holds thousands of blocks, far too many for one
+// response. Like the pages of a book, `?page=2&limit=20` returns items 21 to 40,
+// and the envelope around them says how many there are altogether.
+
+pub const DEFAULT_PAGE_LIMIT: usize = 20;
+pub const MAX_PAGE_LIMIT: usize = 500;
+
+// The envelope every list endpoint answers with
+#[derive(Debug, Serialize)]
+struct Page<T> {
+    items: Vec<T>,
+    page: usize,
+    limit: usize,
+    total_items: usize,
+    total_pages: usize,
+}
+
+impl<T> Page<T> {
+    fn map<U>(self, f: impl FnMut(T) -> U) -> Page<U> {
+        Page {
+            items: self.items.into_iter().map(f).collect(),
+            page: self.page,
+            limit: self.limit,
+            total_items: self.total_items,
+            total_pages: self.total_pages,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Paging {
+    // Counted from 1
+    page: usize,
+    limit: usize,
+}
+
+impl Paging {
+    fn new(page: Option<usize>, limit: Option<usize>) -> Result<Paging, String> {
+        let page = page.unwrap_or(1);
+        let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT);
+        if page == 0 {
+            return Err("page starts at 1".to_string());
+        }
+        if !(1..=MAX_PAGE_LIMIT).contains(&limit) {
+            return Err(format!("limit must be between 1 and {}", MAX_PAGE_LIMIT));
+        }
+        Ok(Paging { page, limit })
+    }
+
+    // Cut this page out of every matching item. A page past the end is empty.
+    fn cut<T>(self, items: Vec<T>) -> Page<T> {
+        let total_items = items.len();
+        let items = items
+            .into_iter()
+            .skip((self.page - 1).saturating_mul(self.limit))
+            .take(self.limit)
+            .collect();
+        Page {
+            items,
+            page: self.page,
+            limit: self.limit,
+            total_items,
+            total_pages: total_items.div_ceil(self.limit),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Order {
+    #[default]
+    Asc,
+    Desc,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum TransactionSort {
+    #[default]
+    Block,
+    Amount,
+    Fee,
+}
+
+// `from` and `to` pick a range of block indexes (both included) on both endpoints
+#[derive(Debug, Deserialize)]
+struct BlocksQuery {
+    #[serde(default)]
+    follow: bool,
+    page: Option<usize>,
+    limit: Option<usize>,
+    from: Option<u32>,
+    to: Option<u32>,
+    #[serde(default)]
+    order: Order,
+}
+
+#[derive(Debug, Deserialize)]
+struct TransactionsQuery {
+    page: Option<usize>,
+    limit: Option<usize>,
+    from: Option<u32>,
+    to: Option<u32>,
+    min_amount: Option<u64>,
+    #[serde(default)]
+    sort: TransactionSort,
+    #[serde(default)]
+    order: Order,
+}
+
+#[derive(Debug, Deserialize)]
+struct HistoryQuery {
+    page: Option<usize>,
+    limit: Option<usize>,
+    #[serde(default)]
+    order: Order,
+}
+
+fn in_range(index: u32, from: Option<u32>, to: Option<u32>) -> bool {
+    from.is_none_or(|from| index >= from) && to.is_none_or(|to| index <= to)
+}
+
+fn bad_request(message: impl Display) -> warp::reply::WithStatus<warp::reply::Json> {
+    warp::reply::with_status(
+        warp::reply::json(&json!({ "error": message.to_string() })),
+        warp::http::StatusCode::BAD_REQUEST,
+    )
+}
+
 async fn get_all_blocks(
+    query: BlocksQuery,
     blockchain: Arc<tokio::sync::RwLock<crate::BlockChain>>,
-) -> Result<impl warp::Reply, warp::Rejection> {
-    let blockchain = blockchain.read().await;
-    Ok(warp::reply::json(&*blockchain))
+    event_bus: EventBus,
+    shutdown: watch::Receiver<bool>,
+) -> Result<Box<dyn warp::Reply>, warp::Rejection> {
+    if query.follow {
+        let blocks = follow_blocks(blockchain, event_bus, shutdown).await;
+        return Ok(Box::new(warp::sse::reply(
+            warp::sse::keep_alive().stream(blocks),
+        )));
+    }
+
+    let paging = match Paging::new(query.page, query.limit) {
+        Ok(paging) => paging,
+        Err(e) => return Ok(Box::new(bad_request(e))),
+    };
+    let page = {
+        let blockchain = blockchain.read().await;
+        let mut indexes: Vec<usize> = (0..blockchain.chain.len())
+            .filter(|&index| in_range(index as u32, query.from, query.to))
+            .collect();
+        if query.order == Order::Desc {
+            indexes.reverse();
+        }
+        paging
+            .cut(indexes)
+            .map(|index| blockchain.chain[index].clone())
+    };
+    Ok(Box::new(warp::reply::json(&page)))
+}
+
+// 🎯 What is Server-Sent Events (SSE)?
+// SSE keeps a normal HTTP response open and lets the server keep writing
+// `event: ... / data: ...` messages to it. It's one-way (server -> client),
+// which is all a block explorer following the chain needs.
+
+// What a follower gets told: the next block, or that the chain was swapped for a
+// fork and everything from `fork_index` on is being sent again.
+enum FollowUpdate {
+    Block(crate::Block),
+    Reorg { fork_index: usize },
+}
+
+// How many of the blocks sent so far are still on the chain. Each block links to
+// the one before it, so once one matches, so do all the earlier ones.
+fn still_on_chain(sent: &[String], chain: &[crate::Block]) -> usize {
+    (1..=sent.len().min(chain.len()))
+        .rev()
+        .find(|&n| sent[n - 1] == chain[n - 1].hash)
+        .unwrap_or(0)
+}
+
+// Every block already in the chain, then each new block as it gets appended.
+// We subscribe before taking the snapshot so no block can slip in between.
+// We remember the hash of every block we sent: if `replace_chain` adopts a fork,
+// the client gets a `reorg` event and then the new blocks from the fork point on.
+// When the client disconnects, warp drops the stream and the subscription with it.
+// On shutdown the stream ends, so the server doesn't wait on clients that never leave.
+async fn follow_blocks(
+    blockchain: Arc<tokio::sync::RwLock<crate::BlockChain>>,
+    event_bus: EventBus,
+    mut shutdown: watch::Receiver<bool>,
+) -> impl Stream<Item = Result<warp::sse::Event, serde_json::Error>> {
+    let receiver = event_bus.subscribe();
+    let existing = blockchain.read().await.chain.clone();
+    let sent: Vec<String> = existing.iter().map(|block| block.hash.clone()).collect();
+
+    let updates = stream::unfold(
+        (receiver, blockchain, sent),
+        |(mut receiver, blockchain, mut sent)| async move {
+            loop {
+                match receiver.recv().await {
+                    // Sent after a block is appended or a fork adopted; work out what changed
+                    Ok(BlockchainEvent::BlockchainUpdated { .. }) | Err(RecvError::Lagged(_)) => {
+                        let mut updates = Vec::new();
+                        {
+                            let chain = &blockchain.read().await.chain;
+                            let fork_index = still_on_chain(&sent, chain);
+                            if fork_index < sent.len() {
+                                sent.truncate(fork_index);
+                                updates.push(FollowUpdate::Reorg { fork_index });
+                            }
+                            for block in &chain[sent.len()..] {
+                                sent.push(block.hash.clone());
+                                updates.push(FollowUpdate::Block(block.clone()));
+                            }
+                        }
+                        if updates.is_empty() {
+                            continue;
+                        }
+                        return Some((stream::iter(updates), (receiver, blockchain, sent)));
+                    }
+                    Ok(_) => continue,
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        },
+    )
+    .flatten();
+
+    stream::iter(existing.into_iter().map(FollowUpdate::Block))
+        .chain(updates)
+        .take_until(async move { stopped(&mut shutdown).await })
+        .map(|update| match update {
+            FollowUpdate::Block(block) => warp::sse::Event::default()
+                .event("block")
+                .id(block.index.to_string())
+                .json_data(&block),
+            FollowUpdate::Reorg { fork_index } => warp::sse::Event::default()
+                .event("reorg")
+                .json_data(json!({ "fork_index": fork_index })),
+        })
 }
 
 async fn get_block_by_index(
     index: u32,
     blockchain: Arc<tokio::sync::RwLock<crate::BlockChain>>,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let blockchain = blockchain.read().await;
+    let block = blockchain.read().await.chain.get(index as usize).cloned();
 
-    if let Some(block) = blockchain.chain.get(index as usize) {
-        Ok(warp::reply::json(block))
+    if let Some(block) = block {
+        Ok(warp::reply::json(&block))
     } else {
         Err(warp::reject::not_found())
     }
@@ -226,64 +747,255 @@ async fn get_block_by_index(
 async fn get_blockchain_status(
     blockchain: Arc<tokio::sync::RwLock<crate::BlockChain>>,
     connection_manager: Arc<ConnectionManager>,
+    event_bus: EventBus,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let blockchain = blockchain.read().await;
+    let status = blockchain_status(&blockchain, &connection_manager, &event_bus).await;
+    Ok(warp::reply::json(&status))
+}
+
+// Shared by GET /api/status and the WebSocket `{"get": "status"}` command
+async fn blockchain_status(
+    blockchain: &tokio::sync::RwLock<crate::BlockChain>,
+    connection_manager: &ConnectionManager,
+    event_bus: &EventBus,
+) -> serde_json::Value {
+    let (total_blocks, last_block_hash) = {
+        let blockchain = blockchain.read().await;
+        (
+            blockchain.chain.len(),
+            blockchain.chain.last().map(|b| b.hash.clone()),
+        )
+    };
     let connection_count = connection_manager.connection_count().await;
 
-    let status = json!({
-        "total_blocks": blockchain.chain.len(),
+    json!({
+        "total_blocks": total_blocks,
         "connected_clients": connection_count,
-        "last_block_hash": blockchain.chain.last().map(|b| &b.hash),
+        "dropped_events": event_bus.dropped_events(),
+        "dropped_for_slow_clients": connection_manager.dropped_events(),
+        "last_block_hash": last_block_hash,
         "timestamp": std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
             .as_secs()
-    });
-
-    Ok(warp::reply::json(&status))
+    })
 }
 
 async fn get_all_transactions(
+    query: TransactionsQuery,
     blockchain: Arc<tokio::sync::RwLock<crate::BlockChain>>,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let blockchain = blockchain.read().await;
+    let paging = match Paging::new(query.page, query.limit) {
+        Ok(paging) => paging,
+        Err(e) => return Ok(bad_request(e)),
+    };
+    let page = {
+        let blockchain = blockchain.read().await;
+        let mut matching: Vec<_> = blockchain
+            .chain
+            .iter()
+            .filter(|block| in_range(block.index, query.from, query.to))
+            .flat_map(|block| {
+                block
+                    .data
+                    .transaction_table
+                    .iter()
+                    .map(move |transaction| (block, transaction))
+            })
+            .filter(|(_, transaction)| {
+                query
+                    .min_amount
+                    .is_none_or(|min_amount| transaction.amount >= min_amount)
+            })
+            .collect();
 
-    let mut all_transactions = Vec::new();
+        // Stable sorts, so equal amounts or fees stay in chain order
+        match query.sort {
+            TransactionSort::Block => {}
+            TransactionSort::Amount => matching.sort_by_key(|(_, transaction)| transaction.amount),
+            TransactionSort::Fee => matching.sort_by_key(|(_, transaction)| transaction.fee),
+        }
+        if query.order == Order::Desc {
+            matching.reverse();
+        }
 
-    for (block_index, block) in blockchain.chain.iter().enumerate() {
-        for transaction in &block.data.transaction_table {
-            all_transactions.push(json!({
-                "block_index": block_index,
+        paging.cut(matching).map(|(block, transaction)| {
+            json!({
+                "tx_hash": transaction.hash(),
+                "block_index": block.index,
                 "from": transaction.from,
                 "to": transaction.to,
                 "amount": transaction.amount,
                 "fee": transaction.fee,
+                "nonce": transaction.nonce,
                 "block_hash": block.hash
-            }));
-        }
+            })
+        })
+    };
+
+    Ok(warp::reply::with_status(
+        warp::reply::json(&page),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+// A transaction by its hash (the id `POST /api/transactions` returned). A mined one
+// comes with its block and how many blocks confirm it; a pending one is still
+// waiting in the mempool.
+async fn get_transaction(
+    tx_hash: String,
+    blockchain: Arc<tokio::sync::RwLock<crate::BlockChain>>,
+    mempool: Arc<tokio::sync::Mutex<Mempool>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mined = {
+        let blockchain = blockchain.read().await;
+        blockchain
+            .find_transaction(&tx_hash)
+            .map(|(block, position)| {
+                json!({
+                    "tx_hash": tx_hash,
+                    "status": "confirmed",
+                    "block_index": block.index,
+                    "block_hash": block.hash,
+                    "position": position,
+                    "confirmations": blockchain.chain.len() - block.index as usize,
+                    "timestamp": block.timestamp,
+                    "transaction": block.data.transaction_table[position]
+                })
+            })
+    };
+    if let Some(mined) = mined {
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&mined),
+            warp::http::StatusCode::OK,
+        ));
     }
 
-    Ok(warp::reply::json(&all_transactions))
+    let pending = mempool.lock().await.get(&tx_hash).cloned();
+    Ok(match pending {
+        Some(transaction) => warp::reply::with_status(
+            warp::reply::json(&json!({
+                "tx_hash": tx_hash,
+                "status": "pending",
+                "transaction": transaction
+            })),
+            warp::http::StatusCode::OK,
+        ),
+        None => warp::reply::with_status(
+            warp::reply::json(&json!({ "tx_hash": tx_hash, "status": "unknown" })),
+            warp::http::StatusCode::NOT_FOUND,
+        ),
+    })
+}
+
+// Every mined send and receive of an address, paged like the other lists
+async fn get_address_history(
+    address: String,
+    query: HistoryQuery,
+    blockchain: Arc<tokio::sync::RwLock<crate::BlockChain>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let address = match Address::parse(&address) {
+        Ok(address) => address,
+        Err(e) => return Ok(bad_request(e)),
+    };
+    let paging = match Paging::new(query.page, query.limit) {
+        Ok(paging) => paging,
+        Err(e) => return Ok(bad_request(e)),
+    };
+    let history = blockchain.read().await.address_history(&address);
+
+    Ok(match history {
+        Ok(mut history) => {
+            if query.order == Order::Desc {
+                history.reverse();
+            }
+            warp::reply::with_status(
+                warp::reply::json(&paging.cut(history)),
+                warp::http::StatusCode::OK,
+            )
+        }
+        Err(e) => warp::reply::with_status(
+            warp::reply::json(&json!({ "error": e.to_string() })),
+            warp::http::StatusCode::INTERNAL_SERVER_ERROR,
+        ),
+    })
+}
+
+// Queue a signed transaction for the miner. Rejected ones get a 400 with the reason,
+// including senders that can't afford it.
+async fn submit_transaction(
+    transaction: Transaction,
+    blockchain: Arc<tokio::sync::RwLock<crate::BlockChain>>,
+    mempool: Arc<tokio::sync::Mutex<Mempool>>,
+    event_bus: EventBus,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let submitted = crate::submit_transaction(&blockchain, &mempool, &event_bus, transaction).await;
+
+    Ok(match submitted {
+        Ok(tx_hash) => warp::reply::with_status(
+            warp::reply::json(&json!({ "tx_hash": tx_hash, "status": "pending" })),
+            warp::http::StatusCode::CREATED,
+        ),
+        Err(e) => bad_request(e),
+    })
+}
+
+// Only mined transactions count; pending ones don't change the balance yet.
+// A mistyped address fails its checksum and gets a 400.
+async fn get_balance(
+    address: String,
+    blockchain: Arc<tokio::sync::RwLock<crate::BlockChain>>,
+    mempool: Arc<tokio::sync::Mutex<Mempool>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let address = match Address::parse(&address) {
+        Ok(address) => address,
+        Err(e) => return Ok(bad_request(e)),
+    };
+    let (balance, next_nonce) = {
+        let chain = blockchain.read().await;
+        let ledger = chain.ledger();
+        (
+            ledger.balance(&address),
+            // Counts the transactions still waiting in the mempool too
+            mempool.lock().await.next_nonce(&address, ledger),
+        )
+    };
+
+    Ok(warp::reply::with_status(
+        warp::reply::json(&json!({
+            "address": address,
+            "balance": balance,
+            "next_nonce": next_nonce
+        })),
+        warp::http::StatusCode::OK,
+    ))
 }
 
 async fn get_block_transactions(
     block_index: u32,
     blockchain: Arc<tokio::sync::RwLock<crate::BlockChain>>,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let blockchain = blockchain.read().await;
+    let block = blockchain
+        .read()
+        .await
+        .chain
+        .get(block_index as usize)
+        .cloned();
 
-    if let Some(block) = blockchain.chain.get(block_index as usize) {
+    if let Some(block) = block {
         let transactions = block
             .data
             .transaction_table
             .iter()
             .map(|transaction| {
                 json!({
+                    "tx_hash": transaction.hash(),
                     "block_index": block_index,
                     "from": transaction.from,
                     "to": transaction.to,
                     "amount": transaction.amount,
                     "fee": transaction.fee,
+                    "nonce": transaction.nonce,
                     "block_hash": block.hash,
                     "signature": transaction.signature
                 })
@@ -295,3 +1007,469 @@ async fn get_block_transactions(
         Err(warp::reject::not_found())
     }
 }
+
+// Unknown hashes are reported as a pending (unconfirmed) 404
+async fn get_transaction_receipt(
+    tx_hash: String,
+    blockchain: Arc<tokio::sync::RwLock<crate::BlockChain>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let receipt = blockchain.read().await.receipt(&tx_hash);
+
+    Ok(match receipt {
+        Some(receipt) => {
+            warp::reply::with_status(warp::reply::json(&receipt), warp::http::StatusCode::OK)
+        }
+        None => warp::reply::with_status(
+            warp::reply::json(&json!({ "tx_hash": tx_hash, "confirmed": false })),
+            warp::http::StatusCode::NOT_FOUND,
+        ),
+    })
+}
+
+// Demo only: the secret key is returned so the caller can sign with it
+async fn create_demo_wallet() -> Result<impl warp::Reply, warp::Rejection> {
+    let wallet = Wallet::generate();
+
+    Ok(warp::reply::json(&json!({
+        "address": wallet.address(),
+        "public_key": wallet.public_key_hex(),
+        "secret_key": wallet.secret_key_hex()
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{address, mine_into, sample_block};
+    use crate::{Block, BlockChain, mine_and_append};
+
+    // Reads from the raw HTTP connection until `needle` shows up in the response
+    async fn read_until(stream: &mut tokio::net::TcpStream, received: &mut String, needle: &str) {
+        use tokio::io::AsyncReadExt;
+
+        let mut buf = [0u8; 4096];
+        while !received.contains(needle) {
+            let n = tokio::time::timeout(Duration::from_secs(15), stream.read(&mut buf))
+                .await
+                .unwrap_or_else(|_| panic!("timed out waiting for {:?}", needle))
+                .unwrap();
+            assert!(n > 0, "stream closed before {:?} arrived", needle);
+            received.push_str(&String::from_utf8_lossy(&buf[..n]));
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn shutdown_sends_clients_a_close_frame_and_stops_the_server() {
+        let event_bus = EventBus::new();
+        let connection_manager = Arc::new(ConnectionManager::new());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (stop, shutdown) = watch::channel(false);
+        let blockchain = Arc::new(tokio::sync::RwLock::new(BlockChain::new().unwrap()));
+        let server = WebSocketServer::new(
+            blockchain,
+            event_bus.clone(),
+            Arc::clone(&connection_manager),
+        );
+        let server = tokio::spawn(async move { server.serve(listener, shutdown).await });
+
+        let (mut client, _) = tokio_tungstenite::connect_async(format!("ws://{}", addr))
+            .await
+            .unwrap();
+        while connection_manager.connection_count().await == 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        stop.send(true).unwrap();
+        let close = tokio::time::timeout(Duration::from_secs(5), client.next())
+            .await
+            .unwrap();
+        assert!(matches!(
+            close,
+            Some(Ok(Message::Close(Some(frame)))) if frame.code == CloseCode::Away
+        ));
+
+        tokio::time::timeout(Duration::from_secs(5), server)
+            .await
+            .expect("server did not stop")
+            .unwrap();
+        assert_eq!(connection_manager.connection_count().await, 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn clients_pick_their_events_and_ask_for_status() {
+        let event_bus = EventBus::new();
+        let blockchain = Arc::new(tokio::sync::RwLock::new(BlockChain::new().unwrap()));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (_stop, shutdown) = watch::channel(false);
+        let server = WebSocketServer::new(
+            blockchain,
+            event_bus.clone(),
+            Arc::new(ConnectionManager::new()),
+        );
+        tokio::spawn(async move { server.serve(listener, shutdown).await });
+
+        let (mut client, _) = tokio_tungstenite::connect_async(format!("ws://{}", addr))
+            .await
+            .unwrap();
+        let mut ask = async |request: &str| {
+            client
+                .send(Message::Text(request.to_string()))
+                .await
+                .unwrap();
+            let reply = tokio::time::timeout(Duration::from_secs(5), client.next())
+                .await
+                .unwrap();
+            match reply {
+                Some(Ok(Message::Text(text))) => text,
+                other => panic!("unexpected reply {:?}", other),
+            }
+        };
+
+        assert_eq!(ask("ping").await, "pong");
+        let status: serde_json::Value =
+            serde_json::from_str(&ask(r#"{"get": "status"}"#).await).unwrap();
+        assert_eq!(status["status"]["total_blocks"], 1);
+        assert_eq!(status["status"]["connected_clients"], 1);
+        assert!(
+            ask(r#"{"subscribe": ["NoSuchEvent"]}"#)
+                .await
+                .contains("error")
+        );
+        assert!(ask("hello").await.contains("error"));
+        assert_eq!(
+            ask(r#"{"subscribe": ["BlockMined", "DifficultyAdjusted"]}"#).await,
+            r#"{"subscribed":["BlockMined","DifficultyAdjusted"]}"#
+        );
+        assert_eq!(
+            ask(r#"{"unsubscribe": ["DifficultyAdjusted"]}"#).await,
+            r#"{"subscribed":["BlockMined"]}"#
+        );
+
+        // Only the subscribed event gets through
+        event_bus.broadcast(BlockchainEvent::BlockchainUpdated {
+            total_blocks: 2,
+            total_transactions: 0,
+        });
+        event_bus.broadcast(BlockchainEvent::BlockMined {
+            block_index: 1,
+            hash: "abc".to_string(),
+            miner: "miner".to_string(),
+            timestamp: 0,
+            transactions_count: 0,
+            hash_attempts: 1,
+            mining_millis: 0,
+            hash_rate: 0,
+        });
+        let received = tokio::time::timeout(Duration::from_secs(5), client.next())
+            .await
+            .unwrap();
+        assert!(matches!(
+            received,
+            Some(Ok(Message::Text(text))) if text.starts_with(r#"{"BlockMined""#)
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn follow_streams_existing_blocks_then_new_ones() {
+        use tokio::io::AsyncWriteExt;
+
+        let blockchain = Arc::new(tokio::sync::RwLock::new(BlockChain::new().unwrap()));
+        let event_bus = EventBus::new();
+        let (stop, shutdown) = watch::channel(false);
+        let routes = create_api_routes(
+            Arc::clone(&blockchain),
+            Arc::new(ConnectionManager::new()),
+            event_bus.clone(),
+            Arc::new(tokio::sync::Mutex::new(Mempool::new())),
+            shutdown.clone(),
+        );
+        let (addr, server) = warp::serve(routes).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET /api/blocks?follow=true HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+
+        // The initial dump has the genesis block
+        let mut received = String::new();
+        read_until(&mut stream, &mut received, "id:0").await;
+        assert!(received.contains("text/event-stream"));
+        assert!(!received.contains("id:1"));
+
+        // A block mined while the stream is open arrives on it
+        let block = Block::new(1, String::new(), sample_block().data).unwrap();
+        mine_and_append(&blockchain, block, &event_bus, "miner")
+            .await
+            .unwrap();
+        read_until(&mut stream, &mut received, "id:1").await;
+        assert!(received.contains("\"index\":1"));
+
+        // Shutting down ends the stream instead of leaving the client hanging
+        stop.send(true).unwrap();
+        // The last chunk of a chunked HTTP body is empty
+        read_until(&mut stream, &mut received, "\r\n0\r\n\r\n").await;
+    }
+
+    #[tokio::test]
+    async fn followers_hear_about_a_reorg_and_get_the_fork_resent() {
+        let mut ours = BlockChain::new().unwrap();
+        let mut fork = ours.clone();
+        mine_into(&mut ours, sample_block()).unwrap();
+        for (index, amount) in [(1, 20), (2, 30)] {
+            let data = crate::MultipleTransactions {
+                transaction_table: vec![Transaction::coinbase(&address("peer"), amount, index)],
+            };
+            mine_into(&mut fork, Block::new(index, String::new(), data).unwrap()).unwrap();
+        }
+
+        let blockchain = Arc::new(tokio::sync::RwLock::new(ours));
+        let event_bus = EventBus::new();
+        let (_stop, shutdown) = watch::channel(false);
+        let mut events =
+            Box::pin(follow_blocks(Arc::clone(&blockchain), event_bus.clone(), shutdown).await);
+        let mut next = async || {
+            tokio::time::timeout(Duration::from_secs(5), events.next())
+                .await
+                .unwrap()
+                .unwrap()
+                .unwrap()
+                .to_string()
+        };
+        assert!(next().await.contains("id:0"));
+        assert!(next().await.contains("id:1"));
+
+        // The peer's longer chain replaces our block 1 with its own
+        assert!(
+            blockchain
+                .write()
+                .await
+                .replace_chain(fork.chain.clone(), &event_bus)
+                .unwrap()
+        );
+        let reorg = next().await;
+        assert!(reorg.contains("event:reorg"));
+        assert!(reorg.contains(r#""fork_index":1"#));
+        for index in 1..=2 {
+            let block = next().await;
+            assert!(block.contains(&format!("id:{}", index)));
+            assert!(block.contains(&fork.chain[index].hash));
+        }
+    }
+
+    #[tokio::test]
+    async fn lists_come_in_pages_with_filters_and_sorting() {
+        let mut chain = BlockChain::new().unwrap();
+        for amount in [30, 10, 20, 40] {
+            let mut block = sample_block();
+            block.index = chain.chain.len() as u32;
+            block.data.transaction_table[0].amount = amount;
+            mine_into(&mut chain, block).unwrap();
+        }
+        let (_stop, shutdown) = watch::channel(false);
+        let routes = create_api_routes(
+            Arc::new(tokio::sync::RwLock::new(chain)),
+            Arc::new(ConnectionManager::new()),
+            EventBus::new(),
+            Arc::new(tokio::sync::Mutex::new(Mempool::new())),
+            shutdown,
+        );
+        let get = async |path: &str| {
+            let response = warp::test::request().path(path).reply(&routes).await;
+            let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+            (response.status(), body)
+        };
+        let field = |body: &serde_json::Value, name: &str| -> Vec<u64> {
+            body["items"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|item| item[name].as_u64().unwrap())
+                .collect()
+        };
+
+        let (_, page) = get("/api/blocks?page=2&limit=2").await;
+        assert_eq!(field(&page, "index"), vec![2, 3]);
+        assert_eq!(page["total_items"], 5);
+        assert_eq!(page["total_pages"], 3);
+
+        let (_, page) = get("/api/blocks?from=1&to=3&order=desc").await;
+        assert_eq!(field(&page, "index"), vec![3, 2, 1]);
+        let (_, page) = get("/api/blocks?page=9").await;
+        assert_eq!(page["items"], json!([]));
+
+        let (_, page) = get("/api/transactions?min_amount=20&sort=amount&order=desc").await;
+        assert_eq!(field(&page, "amount"), vec![40, 30, 20]);
+        assert_eq!(field(&page, "block_index"), vec![4, 1, 3]);
+        assert_eq!(page["total_items"], 3);
+
+        let (status, body) = get("/api/blocks?limit=0").await;
+        assert_eq!(status, 400);
+        assert!(body["error"].as_str().unwrap().contains("limit"));
+    }
+
+    #[tokio::test]
+    async fn submitted_transactions_land_in_the_mempool() {
+        let wallet = Wallet::generate();
+        let chain = BlockChain::new()
+            .unwrap()
+            .with_genesis_allocations(&[(wallet.address(), 100)]);
+        let mempool = Arc::new(tokio::sync::Mutex::new(Mempool::new()));
+        let (_stop, shutdown) = watch::channel(false);
+        let routes = create_api_routes(
+            Arc::new(tokio::sync::RwLock::new(chain)),
+            Arc::new(ConnectionManager::new()),
+            EventBus::new(),
+            Arc::clone(&mempool),
+            shutdown,
+        );
+        let transaction = wallet.create_signed_transaction(&address("bob"), 10, 1, 0);
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/api/transactions")
+            .json(&transaction)
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), 201);
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body["tx_hash"], transaction.hash());
+        assert_eq!(mempool.lock().await.len(), 1);
+
+        // The same transaction again is refused
+        let response = warp::test::request()
+            .method("POST")
+            .path("/api/transactions")
+            .json(&transaction)
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), 400);
+        assert_eq!(mempool.lock().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn transactions_are_found_by_hash_and_listed_in_address_history() {
+        let wallet = Wallet::generate();
+        let blockchain = Arc::new(tokio::sync::RwLock::new(
+            BlockChain::new()
+                .unwrap()
+                .with_genesis_allocations(&[(wallet.address(), 100)]),
+        ));
+        let mempool = Arc::new(tokio::sync::Mutex::new(Mempool::new()));
+        let (_stop, shutdown) = watch::channel(false);
+        let routes = create_api_routes(
+            Arc::clone(&blockchain),
+            Arc::new(ConnectionManager::new()),
+            EventBus::new(),
+            Arc::clone(&mempool),
+            shutdown,
+        );
+        let get = async |path: String| {
+            let response = warp::test::request().path(&path).reply(&routes).await;
+            let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+            (response.status(), body)
+        };
+
+        let transaction = wallet.create_signed_transaction(&address("bob"), 10, 1, 0);
+        let tx_hash = transaction.hash();
+        let ledger = blockchain.read().await.ledger().clone();
+        mempool
+            .lock()
+            .await
+            .submit(transaction.clone(), &ledger)
+            .unwrap();
+        let (status, body) = get(format!("/api/transactions/{}", tx_hash)).await;
+        assert_eq!(status, 200);
+        assert_eq!(body["status"], "pending");
+
+        let block = Block::new(
+            1,
+            String::new(),
+            crate::MultipleTransactions {
+                transaction_table: vec![transaction],
+            },
+        )
+        .unwrap();
+        mine_and_append(&blockchain, block, &EventBus::new(), "miner")
+            .await
+            .unwrap();
+        let (status, body) = get(format!("/api/transactions/{}", tx_hash)).await;
+        assert_eq!(status, 200);
+        assert_eq!(body["status"], "confirmed");
+        assert_eq!(body["block_index"], 1);
+        assert_eq!(body["confirmations"], 1);
+        assert_eq!(body["transaction"]["to"], address("bob").to_string());
+
+        let (status, _) = get("/api/transactions/unknown".to_string()).await;
+        assert_eq!(status, 404);
+
+        let (_, page) = get(format!(
+            "/api/addresses/{}/history?order=desc&limit=1",
+            wallet.address()
+        ))
+        .await;
+        assert_eq!(page["total_items"], 2);
+        assert_eq!(page["items"][0]["direction"], "sent");
+        assert_eq!(page["items"][0]["tx_hash"], tx_hash.as_str());
+        assert_eq!(page["items"][0]["balance"], 89);
+    }
+
+    #[tokio::test]
+    async fn balances_come_from_the_chain_and_overdrafts_are_refused() {
+        let wallet = Wallet::generate();
+        let chain = BlockChain::new()
+            .unwrap()
+            .with_genesis_allocations(&[(wallet.address(), 100)]);
+        let (_stop, shutdown) = watch::channel(false);
+        let routes = create_api_routes(
+            Arc::new(tokio::sync::RwLock::new(chain)),
+            Arc::new(ConnectionManager::new()),
+            EventBus::new(),
+            Arc::new(tokio::sync::Mutex::new(Mempool::new())),
+            shutdown.clone(),
+        );
+
+        let response = warp::test::request()
+            .path(&format!("/api/balances/{}", wallet.address()))
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), 200);
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body["balance"], 100);
+        assert_eq!(body["next_nonce"], 0);
+
+        let response = warp::test::request()
+            .path(&format!("/api/balances/{}", address("nobody")))
+            .reply(&routes)
+            .await;
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(body["balance"], 0);
+
+        // A name or a mistyped address fails the checksum
+        let response = warp::test::request()
+            .path("/api/balances/nobody")
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), 400);
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert!(
+            body["error"]
+                .as_str()
+                .unwrap()
+                .contains("is not a valid address")
+        );
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/api/transactions")
+            .json(&wallet.create_signed_transaction(&address("bob"), 100, 1, 0))
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), 400);
+        let body: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert!(body["error"].as_str().unwrap().contains("needs 101"));
+    }
+}
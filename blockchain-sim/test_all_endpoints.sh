@@ -25,7 +25,7 @@ echo "   Last block hash: $(echo $status | jq -r '.last_block_hash' | cut -c1-16
 echo ""
 echo "3. Testing /api/blocks endpoint..."
 blocks=$(curl -s "$BASE_URL/api/blocks")
-total_blocks=$(echo $blocks | jq '.chain | length')
+total_blocks=$(echo $blocks | jq '.total_items')
 echo "   Total blocks in chain: $total_blocks"
 
 echo ""
@@ -45,12 +45,12 @@ done
 echo ""
 echo "5. Testing /api/transactions endpoint..."
 transactions=$(curl -s "$BASE_URL/api/transactions")
-total_tx=$(echo $transactions | jq 'length')
+total_tx=$(echo $transactions | jq '.total_items')
 echo "   Total transactions: $total_tx"
 
 if [ $total_tx -gt 0 ]; then
     echo "   Sample transaction:"
-    echo $transactions | jq '.[0] | {from, to, amount, fee, block_index}'
+    echo $transactions | jq '.items[0] | {from, to, amount, fee, block_index}'
 fi
 
 echo ""
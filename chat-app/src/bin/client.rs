@@ -12,7 +12,7 @@ use cursive::{
 use serde::{Deserialize, Serialize};
 
 // Importing necessary standard library modules
-use std::{env, error::Error, sync::Arc};
+use std::{env, error::Error, fmt::Display, future::Future, sync::Arc, time::Duration};
 
 // Importing Tokio async utilities
 use tokio::{
@@ -39,6 +39,142 @@ enum MessageType {
     SystemNotification, // Represents system-generated messages (e.g., join/leave notifications)
 }
 
+// Templates used to render incoming messages in the message area.
+// `{timestamp}`, `{username}` and `{content}` are replaced with the message fields.
+#[derive(Debug, Clone)]
+struct MessageFormat {
+    system: String, // Template for system notifications (join/leave)
+    user: String,   // Template for messages sent by users
+}
+
+impl Default for MessageFormat {
+    fn default() -> Self {
+        MessageFormat {
+            system: "┌─[{timestamp}]\n└─ {username} ▶ {content}\n".to_string(),
+            user: "\n[{username} {content}]\n".to_string(),
+        }
+    }
+}
+
+impl MessageFormat {
+    // Build the format from CHAT_SYSTEM_FORMAT / CHAT_USER_FORMAT, falling back to the defaults.
+    // A literal `\n` in the environment value is turned into a newline.
+    fn from_env() -> Self {
+        let default = MessageFormat::default();
+        let template = |var: &str, fallback: String| {
+            env::var(var)
+                .map(|value| value.replace("\\n", "\n"))
+                .unwrap_or(fallback)
+        };
+        MessageFormat {
+            system: template("CHAT_SYSTEM_FORMAT", default.system),
+            user: template("CHAT_USER_FORMAT", default.user),
+        }
+    }
+
+    // Fill in the template in a single pass, so placeholder text that a user typed
+    // into their name or message is copied as is instead of being replaced again.
+    fn format(&self, msg: &ChatMessage) -> String {
+        let template = match msg.message_type {
+            MessageType::SystemNotification => &self.system,
+            MessageType::UserMessage => &self.user,
+        };
+        let fields = [
+            ("{timestamp}", &msg.timestamp),
+            ("{username}", &msg.username),
+            ("{content}", &msg.content),
+        ];
+
+        let mut formatted = String::with_capacity(template.len());
+        let mut rest = template.as_str();
+        while let Some(start) = rest.find('{') {
+            formatted.push_str(&rest[..start]);
+            rest = &rest[start..];
+            match fields
+                .iter()
+                .find(|(placeholder, _)| rest.starts_with(placeholder))
+            {
+                Some((placeholder, value)) => {
+                    formatted.push_str(value);
+                    rest = &rest[placeholder.len()..];
+                }
+                None => {
+                    formatted.push('{');
+                    rest = &rest[1..];
+                }
+            }
+        }
+        formatted.push_str(rest);
+        formatted
+    }
+}
+
+// How persistently the client tries to reach the server on startup.
+// Useful when the client is started before (or together with) the server.
+#[derive(Debug, Clone)]
+struct ConnectRetry {
+    attempts: u32,   // Total number of connection attempts
+    delay: Duration, // Pause between two attempts
+}
+
+impl Default for ConnectRetry {
+    fn default() -> Self {
+        ConnectRetry {
+            attempts: 5,
+            delay: Duration::from_secs(1),
+        }
+    }
+}
+
+impl ConnectRetry {
+    // Read CHAT_CONNECT_ATTEMPTS / CHAT_CONNECT_DELAY_MS, falling back to the defaults.
+    // An attempt count that doesn't fit a u32 falls back too, instead of wrapping to 0.
+    fn from_env() -> Self {
+        let default = ConnectRetry::default();
+        let var = |name: &str| env::var(name).ok();
+        ConnectRetry {
+            attempts: var("CHAT_CONNECT_ATTEMPTS")
+                .and_then(|value| value.parse::<u32>().ok())
+                .map(|attempts| attempts.max(1))
+                .unwrap_or(default.attempts),
+            delay: var("CHAT_CONNECT_DELAY_MS")
+                .and_then(|value| value.parse::<u64>().ok())
+                .map(Duration::from_millis)
+                .unwrap_or(default.delay),
+        }
+    }
+}
+
+// Call `connect` until it succeeds or the attempts run out, printing progress along the way.
+// Takes the connect step as a closure so it can be tested without a real server.
+async fn connect_with_retry<T, E, F, Fut>(retry: &ConnectRetry, mut connect: F) -> Result<T, String>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: Display,
+{
+    let mut last_error = String::new();
+    for attempt in 1..=retry.attempts {
+        println!(
+            "Connecting to the server (attempt {}/{})...",
+            attempt, retry.attempts
+        );
+        match connect().await {
+            Ok(connection) => return Ok(connection),
+            Err(e) => {
+                last_error = e.to_string();
+                if attempt < retry.attempts {
+                    tokio::time::sleep(retry.delay).await;
+                }
+            }
+        }
+    }
+    Err(format!(
+        "Could not connect to the server after {} attempts: {}",
+        retry.attempts, last_error
+    ))
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
     // Fetching username from command-line arguments
@@ -116,9 +252,17 @@ async fn main() -> Result<(), Box<dyn Error>> {
         });
     });
 
-    let stream = TcpStream::connect("127.0.0.1:8082")
-        .await
-        .expect("Failed to conenct the server");
+    let stream = match connect_with_retry(&ConnectRetry::from_env(), || {
+        TcpStream::connect("127.0.0.1:8082")
+    })
+    .await
+    {
+        Ok(stream) => stream,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
     let (reader, mut writer) = stream.into_split();
     writer.write_all(format!("{}\n", username).as_bytes()).await;
 
@@ -129,17 +273,12 @@ async fn main() -> Result<(), Box<dyn Error>> {
     let reader = BufReader::new(reader);
     let mut lines = reader.lines();
     let sink = siv.cb_sink().clone();
+    let message_format = MessageFormat::from_env(); // How messages are displayed
 
     tokio::spawn(async move {
         while let Ok(Some(line)) = lines.next_line().await {
             if let Ok(msg) = serde_json::from_str::<ChatMessage>(&line) {
-                let formatted_msg = match msg.message_type {
-                    MessageType::SystemNotification => format!(
-                        "┌─[{}]\n└─ {} ▶ {}\n",
-                        msg.timestamp, msg.username, msg.content
-                    ),
-                    MessageType::UserMessage => format!("\n[{} {}]\n", msg.username, msg.content),
-                };
+                let formatted_msg = message_format.format(&msg);
                 // Update UI with the new message
                 if sink
                     .send(Box::new(move |siv: &mut Cursive| {
@@ -226,3 +365,107 @@ fn create_retro_theme() -> Theme {
     theme.palette = palette; // Apply the palette
     theme
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(message_type: MessageType) -> ChatMessage {
+        ChatMessage {
+            username: "neo".to_string(),
+            content: "hello".to_string(),
+            timestamp: "12:34:56".to_string(),
+            message_type,
+        }
+    }
+
+    #[test]
+    fn default_format_for_user_message() {
+        let formatted = MessageFormat::default().format(&message(MessageType::UserMessage));
+        assert_eq!(formatted, "\n[neo hello]\n");
+    }
+
+    #[test]
+    fn default_format_for_system_notification() {
+        let formatted = MessageFormat::default().format(&message(MessageType::SystemNotification));
+        assert_eq!(formatted, "┌─[12:34:56]\n└─ neo ▶ hello\n");
+    }
+
+    #[test]
+    fn custom_format_moves_timestamp() {
+        let format = MessageFormat {
+            system: "* {content} ({username})".to_string(),
+            user: "{timestamp} <{username}> {content}".to_string(),
+        };
+        assert_eq!(
+            format.format(&message(MessageType::UserMessage)),
+            "12:34:56 <neo> hello"
+        );
+        assert_eq!(
+            format.format(&message(MessageType::SystemNotification)),
+            "* hello (neo)"
+        );
+    }
+
+    #[test]
+    fn placeholders_typed_by_users_are_not_substituted() {
+        let msg = ChatMessage {
+            username: "{content}".to_string(),
+            content: "see {timestamp} and {username}".to_string(),
+            timestamp: "12:34:56".to_string(),
+            message_type: MessageType::UserMessage,
+        };
+        let format = MessageFormat {
+            system: String::new(),
+            user: "{timestamp} <{username}> {content} {unknown}".to_string(),
+        };
+        assert_eq!(
+            format.format(&msg),
+            "12:34:56 <{content}> see {timestamp} and {username} {unknown}"
+        );
+    }
+
+    #[tokio::test]
+    async fn connect_retry_succeeds_on_third_attempt() {
+        let retry = ConnectRetry {
+            attempts: 5,
+            delay: Duration::from_millis(1),
+        };
+        let mut calls = 0;
+        let result = connect_with_retry(&retry, || {
+            calls += 1;
+            let attempt = calls;
+            async move {
+                if attempt < 3 {
+                    Err("connection refused")
+                } else {
+                    Ok(attempt)
+                }
+            }
+        })
+        .await;
+
+        assert_eq!(result, Ok(3));
+        assert_eq!(calls, 3);
+    }
+
+    #[tokio::test]
+    async fn connect_retry_gives_up_with_last_error() {
+        let retry = ConnectRetry {
+            attempts: 2,
+            delay: Duration::from_millis(1),
+        };
+        let mut calls = 0;
+        let result: Result<(), String> = connect_with_retry(&retry, || {
+            calls += 1;
+            async { Err("connection refused") }
+        })
+        .await;
+
+        assert_eq!(
+            result,
+            Err("Could not connect to the server after 2 attempts: connection refused".to_string())
+        );
+        assert_eq!(calls, 2);
+    }
+}
@@ -0,0 +1,132 @@
+use socket2::{Domain, Protocol, Socket, Type};
+use std::io;
+use std::net::{SocketAddr, TcpListener};
+
+// --- Teaching Note ---
+// `TcpListener::bind` picks the OS defaults for us. Two of them get in the way
+// of a dev server:
+// 1. Without `SO_REUSEADDR`, restarting the server right after stopping it can
+//    fail with "address already in use" while the old socket sits in TIME_WAIT.
+// 2. The accept backlog (how many finished handshakes may wait for `accept`)
+//    can't be chosen.
+// `socket2` exposes the raw socket so we can set both before binding.
+
+pub const DEFAULT_BIND_ADDR: &str = "127.0.0.1:7878";
+pub const DEFAULT_BACKLOG: i32 = 128;
+
+/// Where and how the server listens for connections.
+#[derive(Debug, PartialEq)]
+pub struct ListenerConfig {
+    pub addr: SocketAddr,
+    pub backlog: i32,
+}
+
+impl Default for ListenerConfig {
+    fn default() -> Self {
+        ListenerConfig {
+            addr: DEFAULT_BIND_ADDR.parse().unwrap(),
+            backlog: DEFAULT_BACKLOG,
+        }
+    }
+}
+
+impl ListenerConfig {
+    /// Builds the config from `--bind <addr:port>` and `--backlog <n>`,
+    /// falling back to the defaults for anything that isn't given.
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let mut config = ListenerConfig::default();
+        if let Some(addr) = flag_value(args, "--bind")? {
+            config.addr = parse_bind_addr(addr)?;
+        }
+        if let Some(backlog) = flag_value(args, "--backlog")? {
+            config.backlog = backlog
+                .parse()
+                .ok()
+                .filter(|backlog| *backlog > 0)
+                .ok_or_else(|| {
+                    format!("invalid backlog '{}': expected a positive number", backlog)
+                })?;
+        }
+        Ok(config)
+    }
+
+    /// Creates the listening socket with `SO_REUSEADDR` and the configured backlog.
+    pub fn bind(&self) -> io::Result<TcpListener> {
+        let socket = Socket::new(
+            Domain::for_address(self.addr),
+            Type::STREAM,
+            Some(Protocol::TCP),
+        )?;
+        socket.set_reuse_address(true)?;
+        socket.bind(&self.addr.into())?;
+        socket.listen(self.backlog)?;
+        Ok(socket.into())
+    }
+}
+
+/// Parses an `ip:port` pair such as `127.0.0.1:7878` or `[::1]:8080`.
+pub fn parse_bind_addr(addr: &str) -> Result<SocketAddr, String> {
+    addr.parse().map_err(|_| {
+        format!(
+            "invalid bind address '{}': expected ip:port, e.g. {}",
+            addr, DEFAULT_BIND_ADDR
+        )
+    })
+}
+
+// Returns the argument that follows `flag`, if the flag is present at all.
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Result<Option<&'a str>, String> {
+    match args.iter().position(|arg| arg == flag) {
+        Some(i) => args
+            .get(i + 1)
+            .map(|value| Some(value.as_str()))
+            .ok_or_else(|| format!("{} needs a value", flag)),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_configured_bind_address_and_backlog() {
+        let config = ListenerConfig::from_args(&args(&[
+            "http-server",
+            "--bind",
+            "0.0.0.0:8080",
+            "--backlog",
+            "16",
+        ]))
+        .unwrap();
+
+        assert_eq!(config.addr, "0.0.0.0:8080".parse().unwrap());
+        assert_eq!(config.backlog, 16);
+        assert_eq!(
+            ListenerConfig::from_args(&args(&["http-server"])).unwrap(),
+            ListenerConfig::default()
+        );
+    }
+
+    #[test]
+    fn bad_address_is_an_error() {
+        assert!(parse_bind_addr("localhost").is_err());
+        assert!(parse_bind_addr("127.0.0.1:99999").is_err());
+        assert!(ListenerConfig::from_args(&args(&["http-server", "--bind"])).is_err());
+        assert!(ListenerConfig::from_args(&args(&["http-server", "--backlog", "0"])).is_err());
+    }
+
+    #[test]
+    fn binds_an_ephemeral_port() {
+        let config = ListenerConfig {
+            addr: "127.0.0.1:0".parse().unwrap(),
+            backlog: 4,
+        };
+        let listener = config.bind().unwrap();
+        assert_ne!(listener.local_addr().unwrap().port(), 0);
+    }
+}
@@ -0,0 +1,187 @@
+use crate::thread_pool::ThreadPool;
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex, mpsc};
+use std::thread;
+use std::time::Duration;
+
+// --- Teaching Note ---
+// Live reload has two halves. A file watcher (the `notify` crate) tells us when
+// something in the served directory changes, and browsers long-poll
+// `/__livereload?since=N` until the change counter moves past `N`.
+// A `Condvar` lets those waiting requests sleep instead of spinning.
+// Each waiting request still needs a thread to sleep on, so they get a pool of
+// their own: a few open tabs can't starve the request workers, and a lot of
+// them can't start an unbounded number of threads either.
+
+/// How many long-polls can wait at once; any more queue for a free thread.
+const MAX_WAITERS: usize = 16;
+
+/// How long a long-poll waits for a change before answering anyway.
+const LONG_POLL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Script injected into served HTML pages. It long-polls the server and
+/// reloads the page once the change counter differs from the one it was served with.
+pub const RELOAD_SCRIPT: &str = r#"<script>
+(function poll(version) {
+  fetch('/__livereload?since=' + version)
+    .then(function (res) { return res.json(); })
+    .then(function (data) { data.version !== version ? location.reload() : poll(version); })
+    .catch(function () { setTimeout(function () { poll(version); }, 1000); });
+})(__VERSION__);
+</script>"#;
+
+/// A counter that is bumped every time a watched file changes.
+pub struct ChangeNotifier {
+    version: Mutex<u64>,
+    changed: Condvar,
+}
+
+impl ChangeNotifier {
+    pub fn new() -> Self {
+        ChangeNotifier {
+            version: Mutex::new(0),
+            changed: Condvar::new(),
+        }
+    }
+
+    pub fn version(&self) -> u64 {
+        *self.version.lock().unwrap()
+    }
+
+    pub fn notify_change(&self) {
+        *self.version.lock().unwrap() += 1;
+        self.changed.notify_all();
+    }
+
+    /// Blocks until the version moves past `since` or `timeout` elapses,
+    /// then returns the current version.
+    pub fn wait_for_change(&self, since: u64, timeout: Duration) -> u64 {
+        let version = self.version.lock().unwrap();
+        let (version, _) = self
+            .changed
+            .wait_timeout_while(version, timeout, |version| *version <= since)
+            .unwrap();
+        *version
+    }
+}
+
+/// Watches `dir` recursively and sends the path of every created, modified
+/// or removed file down the returned channel.
+///
+/// The watcher stops as soon as it is dropped, so the caller must keep it alive.
+pub fn watch_directory(
+    dir: &Path,
+) -> notify::Result<(RecommendedWatcher, mpsc::Receiver<PathBuf>)> {
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |result: notify::Result<Event>| {
+        let Ok(event) = result else {
+            return;
+        };
+        if event.kind.is_create() || event.kind.is_modify() || event.kind.is_remove() {
+            for path in event.paths {
+                let _ = sender.send(path);
+            }
+        }
+    })?;
+    watcher.watch(dir, RecursiveMode::Recursive)?;
+    Ok((watcher, receiver))
+}
+
+/// The state needed to serve a directory in `--watch` mode.
+pub struct LiveReload {
+    root: PathBuf,
+    notifier: Arc<ChangeNotifier>,
+    waiters: ThreadPool,
+    _watcher: RecommendedWatcher,
+}
+
+impl LiveReload {
+    /// Starts watching `root` and forwards every change to a shared `ChangeNotifier`.
+    pub fn start(root: PathBuf) -> notify::Result<LiveReload> {
+        let (watcher, changes) = watch_directory(&root)?;
+        let notifier = Arc::new(ChangeNotifier::new());
+
+        let forward_to = Arc::clone(&notifier);
+        thread::spawn(move || {
+            for path in changes {
+                println!("File changed: {}", path.display());
+                forward_to.notify_change();
+            }
+        });
+
+        Ok(LiveReload {
+            root,
+            notifier,
+            waiters: ThreadPool::builder()
+                .size(MAX_WAITERS)
+                .thread_name("live-reload")
+                .build(),
+            _watcher: watcher,
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn notifier(&self) -> &ChangeNotifier {
+        &self.notifier
+    }
+
+    /// Calls `reply` with the current version once it moves past `since`, or
+    /// after `LONG_POLL_TIMEOUT`. The wait happens on the long-poll threads, so
+    /// this returns straight away.
+    pub fn on_change<F>(&self, since: u64, reply: F)
+    where
+        F: FnOnce(u64) + Send + 'static,
+    {
+        let notifier = Arc::clone(&self.notifier);
+        self.waiters
+            .execute(move || reply(notifier.wait_for_change(since, LONG_POLL_TIMEOUT)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::time::Instant;
+
+    #[test]
+    fn modifying_a_watched_file_sends_a_notification() {
+        let dir = std::env::temp_dir().join(format!("live-reload-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("index.html");
+        fs::write(&file, "<h1>before</h1>").unwrap();
+
+        let (_watcher, changes) = watch_directory(&dir).unwrap();
+        fs::write(&file, "<h1>after</h1>").unwrap();
+
+        let changed = changes.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(changed.file_name(), file.file_name());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn wait_for_change_wakes_up_on_notify() {
+        let notifier = Arc::new(ChangeNotifier::new());
+        let waiter = Arc::clone(&notifier);
+        let handle = thread::spawn(move || waiter.wait_for_change(0, Duration::from_secs(5)));
+
+        thread::sleep(Duration::from_millis(50));
+        notifier.notify_change();
+
+        assert_eq!(handle.join().unwrap(), 1);
+    }
+
+    #[test]
+    fn wait_for_change_times_out_without_changes() {
+        let notifier = ChangeNotifier::new();
+        let start = Instant::now();
+
+        assert_eq!(notifier.wait_for_change(0, Duration::from_millis(50)), 0);
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+}
@@ -1,10 +1,16 @@
 // We need to declare the new module so that Rust knows to look for `thread_pool.rs`
+mod listener;
+mod live_reload;
 mod thread_pool;
 
+use crate::listener::ListenerConfig;
+use crate::live_reload::{LiveReload, RELOAD_SCRIPT};
 use crate::thread_pool::ThreadPool;
 use std::collections::HashMap;
 use std::io::{Read, Write};
-use std::net::{TcpListener, TcpStream};
+use std::net::TcpStream;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
 
 // --- Teaching Note ---
 // The old, unimplemented ThreadPool, Worker, and Job structs that were here have been removed.
@@ -17,6 +23,8 @@ pub struct Request {
     pub path: String,
     pub headers: HashMap<String, String>,
     pub query: HashMap<String, String>,
+    // Everything after the `?`, as it was sent; empty without a query.
+    pub query_string: String,
     pub content: String,
 }
 
@@ -24,52 +32,97 @@ pub struct Request {
 pub struct Response {
     status_text: String,
     headers: Vec<(String, String)>,
-    body: String,
+    // Bytes rather than a `String`, so images and fonts can be served too.
+    body: Vec<u8>,
 }
 
 impl Response {
-    pub fn json(status: u16, body: &str, headers: Option<Vec<(String, String)>>) -> Self {
+    pub fn new(
+        status: u16,
+        content_type: &str,
+        body: &str,
+        headers: Option<Vec<(String, String)>>,
+    ) -> Self {
+        Self::from_bytes(status, content_type, body.as_bytes().to_vec(), headers)
+    }
+
+    pub fn from_bytes(
+        status: u16,
+        content_type: &str,
+        body: Vec<u8>,
+        headers: Option<Vec<(String, String)>>,
+    ) -> Self {
         let content_len = body.len();
         let predetermined_headers = vec![
-            ("Content-Type".to_string(), "application/json".to_string()),
+            ("Content-Type".to_string(), content_type.to_string()),
             ("Content-Length".to_string(), content_len.to_string()),
         ];
 
-        let headers = headers.unwrap_or_else(|| vec![]);
+        let headers = headers.unwrap_or_default();
+
+        Self {
+            status_text: Self::status_text(status),
+            headers: [predetermined_headers, headers].concat(),
+            body,
+        }
+    }
+
+    pub fn json(status: u16, body: &str, headers: Option<Vec<(String, String)>>) -> Self {
+        Self::new(status, "application/json", body, headers)
+    }
 
-        let status_text = match status {
+    /// Builds a redirect to `location` with an empty body.
+    ///
+    /// Only 301, 302, 307 and 308 are accepted; any other status is an error.
+    pub fn redirect(status: u16, location: &str) -> Result<Self, String> {
+        if !matches!(status, 301 | 302 | 307 | 308) {
+            return Err(format!("{} is not a redirect status", status));
+        }
+
+        Ok(Self {
+            status_text: Self::status_text(status),
+            headers: vec![
+                ("Location".to_string(), location.to_string()),
+                ("Content-Length".to_string(), "0".to_string()),
+            ],
+            body: Vec::new(),
+        })
+    }
+
+    fn status_text(status: u16) -> String {
+        match status {
             200 => "200 OK".to_string(),
+            301 => "301 Moved Permanently".to_string(),
+            302 => "302 Found".to_string(),
+            307 => "307 Temporary Redirect".to_string(),
+            308 => "308 Permanent Redirect".to_string(),
             400 => "400 Bad Request".to_string(),
+            404 => "404 Not Found".to_string(),
             500 => "500 Internal Server Error".to_string(),
             _ => format!("{} Unknown ", status),
-        };
-
-        Self {
-            status_text,
-            headers: [predetermined_headers, headers].concat(),
-            body: body.to_string(),
         }
     }
 
-    pub fn resolve(response: &Response) -> String {
+    pub fn resolve(response: &Response) -> Vec<u8> {
         let mut response_str = format!(
-            "HTTP/1.1 {}
+            "HTTP/1.1 {}
 ",
             response.status_text
         );
 
         for (key, value) in &response.headers {
             response_str.push_str(&format!(
-                "{}: {}
+                "{}: {}
 ",
                 key, value
             ));
         }
 
         response_str.push_str("\r\n");
-        response_str.push_str(&response.body);
 
-        response_str
+        let mut bytes = response_str.into_bytes();
+        bytes.extend_from_slice(&response.body);
+        bytes
     }
 }
 
@@ -106,6 +159,10 @@ impl Request {
         let full_path = parts.next().unwrap();
         let path_and_query: Vec<&str> = full_path.split('?').collect();
         let path = path_and_query[0].to_string();
+        let query_string = full_path
+            .split_once('?')
+            .map(|(_, query)| query.to_string())
+            .unwrap_or_default();
 
         if path_and_query.len() > 1 {
             let query_string = path_and_query[1..].join("");
@@ -118,8 +175,11 @@ impl Request {
         }
 
         request_lines.remove(0);
-        let blank_line_index = request_lines.iter().position(|&line| line == "\r\n").unwrap();
-        let mut body_lines = &mut request_lines.split_off(blank_line_index);
+        let blank_line_index = request_lines
+            .iter()
+            .position(|&line| line == "\r\n")
+            .unwrap();
+        let body_lines = &mut request_lines.split_off(blank_line_index);
         body_lines.remove(0);
         let body_content = body_lines.join("");
 
@@ -137,6 +197,7 @@ impl Request {
             path,
             headers: header_map,
             query: query_params,
+            query_string,
             content: body_content,
         })
     }
@@ -145,19 +206,65 @@ impl Request {
 fn main() {
     println!("Working on Http from scratch");
 
-    let listener = TcpListener::bind("127.0.0.1:7878").unwrap();
-    println!("Server listening on port 7878 with a thread pool.");
+    // `--watch <dir>` serves `dir` as static files and reloads browsers when it changes.
+    let args: Vec<String> = std::env::args().collect();
+    let live_reload = match args.iter().position(|arg| arg == "--watch") {
+        Some(i) => {
+            let Some(dir) = args.get(i + 1) else {
+                eprintln!("--watch needs a directory to serve");
+                return;
+            };
+            match LiveReload::start(PathBuf::from(dir)) {
+                Ok(live_reload) => {
+                    println!("Watching {} for changes.", dir);
+                    Some(Arc::new(live_reload))
+                }
+                Err(e) => {
+                    eprintln!("Failed to watch {}: {}", dir, e);
+                    return;
+                }
+            }
+        }
+        None => None,
+    };
+
+    // `--bind <ip:port>` and `--backlog <n>` control the listening socket.
+    let listener_config = match ListenerConfig::from_args(&args) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    let listener = match listener_config.bind() {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("Failed to bind {}: {}", listener_config.addr, e);
+            return;
+        }
+    };
+    println!(
+        "Server listening on {} with a thread pool.",
+        listener_config.addr
+    );
 
     // --- Teaching Note ---
     // Here we create our new ThreadPool.
     // A size of 4 is a common default. In a real-world application, this might be
     // configured based on the number of CPU cores on the machine.
-    let pool = ThreadPool::new(4);
+    let pool = ThreadPool::builder()
+        .size(4)
+        .thread_name("http-worker")
+        .build();
 
     for stream in listener.incoming() {
         match stream {
             Ok(stream) => {
-                println!("Connection Established!");
+                println!(
+                    "Connection Established! ({} active, {} queued jobs)",
+                    pool.active_count(),
+                    pool.queued_count()
+                );
 
                 // --- Teaching Note ---
                 // This is the core change. Instead of spawning an infinite number of threads,
@@ -166,8 +273,9 @@ fn main() {
                 // The `move` keyword is used to transfer ownership of the `stream` variable
                 // to the closure, which is necessary because the closure will be run on a
                 // different thread.
+                let live_reload = live_reload.clone();
                 pool.execute(move || {
-                    handle_connection(stream);
+                    handle_connection(stream, live_reload.as_deref());
                 });
 
                 /*
@@ -186,22 +294,53 @@ fn main() {
         }
     }
 
+    // Let in-flight requests finish before the pool is dropped.
+    pool.join();
+    for (id, completed) in pool.worker_stats() {
+        println!("Worker {} handled {} requests.", id, completed);
+    }
     println!("Shutting down main thread.");
 }
 
-fn handle_connection(mut stream: TcpStream) {
+fn handle_connection(mut stream: TcpStream, live_reload: Option<&LiveReload>) {
     let req = Request::new(&stream);
+    // A live reload long-poll can hold its connection for 30s. It waits on the
+    // live reload's own threads, otherwise a few open browser tabs would tie up
+    // every pool worker and normal page loads would hang behind them.
+    if let (Ok(req), Some(live_reload)) = (&req, live_reload)
+        && req.path == "/__livereload"
+    {
+        let since = req
+            .query
+            .get("since")
+            .and_then(|since| since.parse().ok())
+            .unwrap_or(0);
+        live_reload.on_change(since, move |version| {
+            let payload = format!("{{\"version\": {}}}", version);
+            send_response(&mut stream, &Response::json(200, &payload, None));
+        });
+        return;
+    }
+
     let res = match req {
-        Ok(req) => match req.path.as_str() {
-            "/hello" => {
+        Ok(req) => match (req.path.as_str(), live_reload) {
+            // Normalize trailing slashes, e.g. `/hello/` -> `/hello`. Not in --watch
+            // mode, where a trailing slash means "serve this directory's index.html".
+            (path, None)
+                if let Some(location) = trailing_slash_redirect(path, &req.query_string) =>
+            {
+                Response::redirect(301, &location).expect("301 is a redirect status")
+            }
+            ("/hello", _) => {
                 let def_name = String::from("Shivraj");
-                let name: &String = req.query.get("name").unwrap_or_else(|| &def_name);
+                let name: &String = req.query.get("name").unwrap_or(&def_name);
                 let payload = format!("{{\"message\": \"Hello, {}!\"}}", name);
                 Response::json(200, &payload, None)
             }
+            (path, Some(live_reload)) => serve_static(live_reload, path),
             _ => {
                 let payload = "{{\"message\": \"Invalid Path\"}}";
-                Response::json(400, &payload, None)
+                Response::json(400, payload, None)
             }
         },
         Err(e) => {
@@ -209,11 +348,166 @@ fn handle_connection(mut stream: TcpStream) {
             Response::json(500, &payload, None)
         }
     };
-    let response_str = Response::resolve(&res);
-    match stream.write(response_str.as_bytes()) {
+    send_response(&mut stream, &res);
+}
+
+// The paths `handle_connection` answers outside --watch mode.
+const ROUTES: &[&str] = &["/hello"];
+
+/// Where to redirect a `path` that ends in a slash, e.g. `/hello/?name=x` to
+/// `/hello?name=x`. `None` if there is no trailing slash to drop, or if the
+/// path without it isn't a route either, which is left to the 400.
+fn trailing_slash_redirect(path: &str, query_string: &str) -> Option<String> {
+    if path.len() <= 1 || !path.ends_with('/') {
+        return None;
+    }
+    // `//` and friends are just the root
+    let target = match path.trim_end_matches('/') {
+        "" => "/",
+        trimmed => trimmed,
+    };
+    if !ROUTES.contains(&target) {
+        return None;
+    }
+    if query_string.is_empty() {
+        Some(target.to_string())
+    } else {
+        Some(format!("{}?{}", target, query_string))
+    }
+}
+
+fn send_response(stream: &mut TcpStream, res: &Response) {
+    match stream.write_all(&Response::resolve(res)) {
         Ok(_) => {}
         Err(_) => {
             println!("FAILED DISPATCHED RESPONSE");
         }
     }
-}
\ No newline at end of file
+}
+
+// Serve a file from the watched directory, injecting the reload script into HTML pages.
+fn serve_static(live_reload: &LiveReload, path: &str) -> Response {
+    let relative = Path::new(path.trim_start_matches('/'));
+    // Refuse anything that could escape the served directory.
+    if relative
+        .components()
+        .any(|component| !matches!(component, Component::Normal(_)))
+    {
+        return Response::json(400, "{\"message\": \"Invalid Path\"}", None);
+    }
+
+    let mut file_path = live_reload.root().join(relative);
+    if file_path.is_dir() {
+        file_path.push("index.html");
+    }
+
+    let Ok(mut contents) = std::fs::read(&file_path) else {
+        return Response::json(404, "{\"message\": \"Not Found\"}", None);
+    };
+
+    let content_type = match file_path.extension().and_then(|ext| ext.to_str()) {
+        Some("html") | Some("htm") => "text/html; charset=utf-8",
+        Some("css") => "text/css",
+        Some("js") => "application/javascript",
+        Some("json") => "application/json",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("ico") => "image/x-icon",
+        Some("woff") => "font/woff",
+        Some("woff2") => "font/woff2",
+        Some("wasm") => "application/wasm",
+        _ => "text/plain; charset=utf-8",
+    };
+
+    if content_type.starts_with("text/html") {
+        let script =
+            RELOAD_SCRIPT.replace("__VERSION__", &live_reload.notifier().version().to_string());
+        inject_before_body_end(&mut contents, script.as_bytes());
+    }
+    Response::from_bytes(200, content_type, contents, None)
+}
+
+/// Inserts `script` right before the last `</body>`, or at the end if there is none.
+fn inject_before_body_end(html: &mut Vec<u8>, script: &[u8]) {
+    let end = html
+        .windows(b"</body>".len())
+        .rposition(|window| window == b"</body>")
+        .unwrap_or(html.len());
+    html.splice(end..end, script.iter().copied());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redirect_sets_location_and_status_line() {
+        let response = Response::redirect(302, "/new").unwrap();
+
+        assert_eq!(response.status_text, "302 Found");
+        assert!(
+            response
+                .headers
+                .contains(&("Location".to_string(), "/new".to_string()))
+        );
+        assert!(response.body.is_empty());
+    }
+
+    #[test]
+    fn trailing_slashes_redirect_to_routes_and_keep_the_query() {
+        assert_eq!(
+            trailing_slash_redirect("/hello/", ""),
+            Some("/hello".to_string())
+        );
+        assert_eq!(
+            trailing_slash_redirect("/hello//", "name=x"),
+            Some("/hello?name=x".to_string())
+        );
+
+        // Nothing to drop, or nothing served without the slash either
+        assert_eq!(trailing_slash_redirect("/", ""), None);
+        assert_eq!(trailing_slash_redirect("/hello", ""), None);
+        assert_eq!(trailing_slash_redirect("/missing/", ""), None);
+        assert_eq!(trailing_slash_redirect("//", ""), None);
+    }
+
+    #[test]
+    fn reload_script_goes_before_the_closing_body_tag() {
+        let mut page = b"<body><p>hi</p></body></html>".to_vec();
+        inject_before_body_end(&mut page, b"<script></script>");
+        assert_eq!(page, b"<body><p>hi</p><script></script></body></html>");
+
+        let mut fragment = b"<p>hi</p>".to_vec();
+        inject_before_body_end(&mut fragment, b"<script></script>");
+        assert_eq!(fragment, b"<p>hi</p><script></script>");
+    }
+
+    #[test]
+    fn binary_bodies_are_sent_unchanged() {
+        let png = vec![0x89, b'P', b'N', b'G', 0xff, 0x00];
+        let response = Response::from_bytes(200, "image/png", png.clone(), None);
+
+        let resolved = Response::resolve(&response);
+        assert!(resolved.ends_with(&png));
+        assert!(
+            response
+                .headers
+                .contains(&("Content-Length".to_string(), "6".to_string()))
+        );
+    }
+
+    #[test]
+    fn redirect_accepts_all_redirect_codes() {
+        for status in [301, 302, 307, 308] {
+            assert!(Response::redirect(status, "/").is_ok());
+        }
+    }
+
+    #[test]
+    fn redirect_rejects_non_redirect_status() {
+        assert!(Response::redirect(200, "/new").is_err());
+        assert!(Response::redirect(304, "/new").is_err());
+    }
+}
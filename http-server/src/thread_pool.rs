@@ -1,5 +1,11 @@
 use std::{
-    sync::{Arc, Mutex, mpsc},
+    marker::PhantomData,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        Arc, Condvar, Mutex,
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    },
     thread,
 };
 
@@ -7,11 +13,26 @@ use std::{
 // thread-safe, and self-contained closure that can be executed once.
 type Job = Box<dyn FnOnce() + Send + 'static>;
 
+// How many jobs are waiting in the channel and how many are running right now.
+struct JobCounts {
+    queued: usize,
+    active: usize,
+}
+
+// The counts are shared between the pool and every worker. The `Condvar` lets
+// `join` sleep until the workers report that everything has finished.
+struct JobTracker {
+    counts: Mutex<JobCounts>,
+    idle: Condvar,
+}
+
 pub struct ThreadPool {
     // The workers vector will hold the threads that are waiting to execute jobs.
     workers: Vec<Worker>,
     // The sender is the way we will send Jobs from the ThreadPool to the Workers.
-    sender: mpsc::Sender<Job>,
+    // It lives in an `Option` so that `drop` can take it out and close the channel.
+    sender: Option<mpsc::Sender<Job>>,
+    tracker: Arc<JobTracker>,
 }
 
 impl ThreadPool {
@@ -22,7 +43,21 @@ impl ThreadPool {
     /// # Panics
     ///
     /// The `new` function will panic if the size is zero.
+    // `main` uses the builder to name its threads; `new` stays as the shorthand.
+    #[allow(dead_code)]
     pub fn new(size: usize) -> ThreadPool {
+        ThreadPool::builder().size(size).build()
+    }
+
+    /// Start configuring a pool, e.g. to give its threads a name.
+    pub fn builder() -> ThreadPoolBuilder {
+        ThreadPoolBuilder {
+            size: DEFAULT_POOL_SIZE,
+            thread_name: None,
+        }
+    }
+
+    fn with_config(size: usize, thread_name: Option<String>) -> ThreadPool {
         // It doesn't make sense to have a thread pool with no threads.
         assert!(size > 0);
 
@@ -39,6 +74,14 @@ impl ThreadPool {
         //    access the data (the receiver) at any given time, preventing race conditions.
         let receiver = Arc::new(Mutex::new(receiver));
 
+        let tracker = Arc::new(JobTracker {
+            counts: Mutex::new(JobCounts {
+                queued: 0,
+                active: 0,
+            }),
+            idle: Condvar::new(),
+        });
+
         // Pre-allocate space for our workers.
         let mut workers = Vec::with_capacity(size);
 
@@ -46,10 +89,19 @@ impl ThreadPool {
         for id in 0..size {
             // We clone the Arc for each worker. This increases the reference count,
             // so the receiver will stay alive as long as at least one worker exists.
-            workers.push(Worker::new(id, Arc::clone(&receiver)));
+            workers.push(Worker::new(
+                id,
+                thread_name.as_deref(),
+                Arc::clone(&receiver),
+                Arc::clone(&tracker),
+            ));
         }
 
-        ThreadPool { workers, sender }
+        ThreadPool {
+            workers,
+            sender: Some(sender),
+            tracker,
+        }
     }
 
     /// Executes a new job in the thread pool.
@@ -61,11 +113,181 @@ impl ThreadPool {
     {
         // Create a new job by putting the closure on the heap.
         let job = Box::new(f);
+        // Count the job as queued before a worker can possibly pick it up.
+        self.tracker.counts.lock().unwrap().queued += 1;
         // Send the job down the channel to the workers.
         // `send` returns a `Result`, but we `unwrap` because the only time it can fail
         // is if the receiver has been dropped. In our design, that means the pool is
         // shutting down, and we can't send new jobs anyway.
-        self.sender.send(job).unwrap();
+        self.sender.as_ref().unwrap().send(job).unwrap();
+    }
+
+    /// Blocks until every submitted job has finished and all workers are idle.
+    ///
+    /// Unlike dropping the pool, the workers stay alive and more jobs can be
+    /// submitted afterwards.
+    pub fn join(&self) {
+        let counts = self.tracker.counts.lock().unwrap();
+        let _idle = self
+            .tracker
+            .idle
+            .wait_while(counts, |counts| counts.queued > 0 || counts.active > 0)
+            .unwrap();
+    }
+
+    /// Number of jobs currently being executed by a worker.
+    pub fn active_count(&self) -> usize {
+        self.tracker.counts.lock().unwrap().active
+    }
+
+    /// Number of jobs submitted but not yet picked up by a worker.
+    pub fn queued_count(&self) -> usize {
+        self.tracker.counts.lock().unwrap().queued
+    }
+
+    /// How many jobs each worker has completed, as `(worker id, completed jobs)` pairs.
+    ///
+    /// A big gap between workers points at uneven load distribution.
+    pub fn worker_stats(&self) -> Vec<(usize, usize)> {
+        self.workers
+            .iter()
+            .map(|worker| (worker.id, worker.completed.load(Ordering::SeqCst)))
+            .collect()
+    }
+
+    /// Runs `f` with a `Scope` whose jobs may borrow data from the caller's stack.
+    ///
+    /// Every job spawned on the scope has finished by the time `scope` returns,
+    /// which is what makes the borrowing safe. If any job panics, `scope`
+    /// panics too once all the other jobs are done.
+    // Not used by the server yet; the request handlers only need owned data.
+    #[allow(dead_code)]
+    pub fn scope<'env, F, R>(&self, f: F) -> R
+    where
+        F: for<'scope> FnOnce(&'scope Scope<'scope, 'env>) -> R,
+    {
+        let scope = Scope {
+            pool: self,
+            state: Arc::new(ScopeState {
+                pending: Mutex::new(0),
+                done: Condvar::new(),
+                panicked: AtomicUsize::new(0),
+            }),
+            scope: PhantomData,
+            env: PhantomData,
+        };
+
+        // Even if `f` itself panics, we must not return (or unwind) past this
+        // frame while jobs can still touch the borrowed data.
+        let result = panic::catch_unwind(AssertUnwindSafe(|| f(&scope)));
+        scope.wait();
+
+        match result {
+            Err(payload) => panic::resume_unwind(payload),
+            Ok(_) if scope.state.panicked.load(Ordering::SeqCst) > 0 => {
+                panic!("a job spawned in a ThreadPool scope panicked")
+            }
+            Ok(value) => value,
+        }
+    }
+}
+
+// --- Teaching Note ---
+// `execute` needs `'static` jobs because the pool can't know when a job runs.
+// A scope fixes that by promising to wait for its jobs before it returns, so the
+// jobs only have to outlive the scope (`'scope`), not the whole program.
+// The two lifetimes follow `std::thread::scope`: `'env` is the borrowed data
+// outside the scope, and `'scope` is the scope itself, which is shorter.
+
+// Bookkeeping shared between a scope and the jobs it spawned.
+struct ScopeState {
+    pending: Mutex<usize>,
+    done: Condvar,
+    panicked: AtomicUsize,
+}
+
+/// A handle for spawning jobs that may borrow from the enclosing stack frame.
+/// Created by `ThreadPool::scope`.
+pub struct Scope<'scope, 'env: 'scope> {
+    pool: &'scope ThreadPool,
+    state: Arc<ScopeState>,
+    // `&'scope mut &'scope ()` makes `Scope` invariant over `'scope`, so the
+    // compiler can't shrink or stretch it to make a borrow look valid.
+    scope: PhantomData<&'scope mut &'scope ()>,
+    env: PhantomData<&'env mut &'env ()>,
+}
+
+#[allow(dead_code)]
+impl<'scope, 'env> Scope<'scope, 'env> {
+    /// Queues `f` on the pool. It may borrow anything that outlives the scope.
+    pub fn spawn<F>(&'scope self, f: F)
+    where
+        F: FnOnce() + Send + 'scope,
+    {
+        *self.state.pending.lock().unwrap() += 1;
+
+        let state = Arc::clone(&self.state);
+        let job: Box<dyn FnOnce() + Send + 'scope> = Box::new(move || {
+            // Catch the panic so the worker survives and the scope is still told
+            // that this job is finished.
+            if panic::catch_unwind(AssertUnwindSafe(f)).is_err() {
+                state.panicked.fetch_add(1, Ordering::SeqCst);
+            }
+            let mut pending = state.pending.lock().unwrap();
+            *pending -= 1;
+            if *pending == 0 {
+                state.done.notify_all();
+            }
+        });
+
+        // SAFETY: the job is only allowed to borrow data that lives for `'scope`.
+        // `ThreadPool::scope` waits for `pending` to reach zero before returning,
+        // so the job always finishes before any of that data goes away.
+        let job: Job =
+            unsafe { std::mem::transmute::<Box<dyn FnOnce() + Send + 'scope>, Job>(job) };
+        self.pool.execute(job);
+    }
+
+    fn wait(&self) {
+        let pending = self.state.pending.lock().unwrap();
+        let _done = self
+            .state
+            .done
+            .wait_while(pending, |pending| *pending > 0)
+            .unwrap();
+    }
+}
+
+// Used by `ThreadPool::builder()` when no size is given.
+const DEFAULT_POOL_SIZE: usize = 4;
+
+/// Configures a `ThreadPool` before any threads are spawned.
+pub struct ThreadPoolBuilder {
+    size: usize,
+    thread_name: Option<String>,
+}
+
+impl ThreadPoolBuilder {
+    /// The number of threads in the pool.
+    pub fn size(mut self, size: usize) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// Name the worker threads `{prefix}-{id}`, which shows up in panic
+    /// messages and in tools like `top -H` or a debugger.
+    pub fn thread_name(mut self, prefix: impl Into<String>) -> Self {
+        self.thread_name = Some(prefix.into());
+        self
+    }
+
+    /// Spawn the worker threads.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the size is zero.
+    pub fn build(self) -> ThreadPool {
+        ThreadPool::with_config(self.size, self.thread_name)
     }
 }
 
@@ -78,7 +300,7 @@ impl Drop for ThreadPool {
         // By dropping the sender, we close the channel. This will cause the
         // `receiver.lock().unwrap().recv()` call in the worker threads to return
         // an `Err`. This is the signal for the workers to break their loop and exit.
-        drop(&self.sender);
+        drop(self.sender.take());
 
         // Now we iterate over our workers and join each one.
         for worker in &mut self.workers {
@@ -105,14 +327,31 @@ struct Worker {
     // Each worker has its own thread. The `JoinHandle` allows us to wait for the
     // thread to finish. It's wrapped in an `Option` so we can `take()` it during shutdown.
     thread: Option<thread::JoinHandle<()>>,
+    // Number of jobs this worker has finished, shared with its thread.
+    completed: Arc<AtomicUsize>,
 }
 
 impl Worker {
     /// Creates a new Worker.
     ///
     /// The worker is a spawned thread that continuously waits for jobs on the receiver.
-    fn new(id: usize, receiver: Arc<Mutex<mpsc::Receiver<Job>>>) -> Worker {
-        let thread = thread::spawn(move || {
+    fn new(
+        id: usize,
+        name_prefix: Option<&str>,
+        receiver: Arc<Mutex<mpsc::Receiver<Job>>>,
+        tracker: Arc<JobTracker>,
+    ) -> Worker {
+        let completed = Arc::new(AtomicUsize::new(0));
+        let completed_by_thread = Arc::clone(&completed);
+
+        // `thread::Builder` is like `thread::spawn`, but lets us set options such
+        // as the thread name before the thread starts.
+        let mut builder = thread::Builder::new();
+        if let Some(prefix) = name_prefix {
+            builder = builder.name(format!("{}-{}", prefix, id));
+        }
+
+        let thread = builder.spawn(move || {
             loop {
                 // The core worker loop.
                 // 1. `receiver.lock().unwrap()`: Acquire the mutex lock. This blocks until the
@@ -126,7 +365,26 @@ impl Worker {
                     Ok(job) => {
                         // If we successfully received a job, execute it.
                         println!("Worker {} got a job; executing.", id);
-                        job(); // This calls the `FnOnce` closure.
+                        {
+                            let mut counts = tracker.counts.lock().unwrap();
+                            counts.queued -= 1;
+                            counts.active += 1;
+                        }
+
+                        // This calls the `FnOnce` closure. A panicking job would
+                        // otherwise take the worker down with it and leave `active`
+                        // counted forever, so `join` would never return.
+                        if panic::catch_unwind(AssertUnwindSafe(job)).is_err() {
+                            println!("Worker {} caught a panicking job.", id);
+                        }
+                        completed_by_thread.fetch_add(1, Ordering::SeqCst);
+
+                        // Wake up anyone in `join` once the last job is done.
+                        let mut counts = tracker.counts.lock().unwrap();
+                        counts.active -= 1;
+                        if counts.queued == 0 && counts.active == 0 {
+                            tracker.idle.notify_all();
+                        }
                     }
                     Err(_) => {
                         // If `recv()` returns an error, it means the sender has been dropped
@@ -137,10 +395,138 @@ impl Worker {
                 }
             }
         });
+        // Spawning only fails if the OS refuses to create a thread.
+        let thread = thread.expect("failed to spawn worker thread");
 
         Worker {
             id,
             thread: Some(thread),
+            completed,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn join_waits_for_all_jobs() {
+        let pool = ThreadPool::new(3);
+        let completed = Arc::new(AtomicUsize::new(0));
+
+        for _ in 0..8 {
+            let completed = Arc::clone(&completed);
+            pool.execute(move || {
+                thread::sleep(Duration::from_millis(20));
+                completed.fetch_add(1, Ordering::SeqCst);
+            });
+        }
+        pool.join();
+
+        assert_eq!(completed.load(Ordering::SeqCst), 8);
+        assert_eq!(pool.active_count(), 0);
+        assert_eq!(pool.queued_count(), 0);
+    }
+
+    #[test]
+    fn join_on_idle_pool_returns_immediately() {
+        let pool = ThreadPool::new(2);
+        pool.join();
+
+        // The pool is still usable after joining.
+        let completed = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&completed);
+        pool.execute(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        pool.join();
+        assert_eq!(completed.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn join_returns_after_a_job_panics() {
+        let pool = ThreadPool::new(1);
+        pool.execute(|| panic!("boom"));
+        pool.join();
+        assert_eq!(pool.active_count(), 0);
+
+        // The worker survived and still picks up jobs.
+        let completed = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&completed);
+        pool.execute(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        pool.join();
+        assert_eq!(completed.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn worker_stats_add_up_to_submitted_jobs() {
+        let pool = ThreadPool::builder()
+            .size(3)
+            .thread_name("test-worker")
+            .build();
+
+        for _ in 0..20 {
+            pool.execute(|| {
+                let name = thread::current().name().map(str::to_string);
+                assert!(name.unwrap().starts_with("test-worker-"));
+            });
+        }
+        pool.join();
+
+        let stats = pool.worker_stats();
+        assert_eq!(stats.len(), 3);
+        assert_eq!(stats.iter().map(|(_, done)| done).sum::<usize>(), 20);
+    }
+
+    #[test]
+    fn scoped_jobs_can_mutate_a_local_vector() {
+        let pool = ThreadPool::new(4);
+        let mut numbers: Vec<usize> = (0..100).collect();
+
+        pool.scope(|s| {
+            for chunk in numbers.chunks_mut(10) {
+                s.spawn(move || {
+                    for n in chunk {
+                        *n *= 2;
+                    }
+                });
+            }
+        });
+
+        let expected: Vec<usize> = (0..100).map(|n| n * 2).collect();
+        assert_eq!(numbers, expected);
+    }
+
+    #[test]
+    fn scope_returns_the_closure_result_and_reads_borrowed_data() {
+        let pool = ThreadPool::new(2);
+        let words = vec!["alpha".to_string(), "beta".to_string()];
+        let total = AtomicUsize::new(0);
+
+        let returned = pool.scope(|s| {
+            for word in &words {
+                let total = &total;
+                s.spawn(move || {
+                    total.fetch_add(word.len(), Ordering::SeqCst);
+                });
+            }
+            "done"
+        });
+
+        assert_eq!(returned, "done");
+        assert_eq!(total.load(Ordering::SeqCst), 9);
+    }
+
+    #[test]
+    #[should_panic(expected = "a job spawned in a ThreadPool scope panicked")]
+    fn scope_propagates_job_panics() {
+        let pool = ThreadPool::new(2);
+        pool.scope(|s| {
+            s.spawn(|| panic!("boom"));
+        });
+    }
+}
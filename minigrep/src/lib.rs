@@ -1,65 +1,203 @@
+use clap::Parser;
 use std::error::Error;
-use std::{env, fs};
+use std::io::{self, Read};
+use std::{env, fmt, fs};
 
 pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
-    let contents = fs::read_to_string(config.filename)?;
+    // Like grep, prefix matches with the file name once there is more than one file
+    let show_filename = config.filenames.len() > 1;
 
-    let results = if config.case_sensitive {
-        search(&config.query, &contents)
-    } else {
-        search_case_senstive(&config.query, &contents)
-    };
+    for filename in &config.filenames {
+        let contents = read_input(filename)?;
+        let prefix = if show_filename {
+            format!("{}:", filename)
+        } else {
+            String::new()
+        };
+        let results: Box<dyn Iterator<Item = (usize, &str)>> = if config.case_sensitive {
+            Box::new(search_numbered_iter(&config.query, &contents))
+        } else {
+            Box::new(search_case_insensitive_numbered_iter(
+                &config.query,
+                &contents,
+            ))
+        };
 
-    for line in results {
-        println!("{}", line);
+        if config.count {
+            println!("{}{}", prefix, results.count());
+        } else if config.line_number {
+            for (number, line) in results {
+                println!("{}{}:{}", prefix, number, line);
+            }
+        } else {
+            for (_, line) in results {
+                println!("{}{}", prefix, line);
+            }
+        }
     }
     Ok(())
 }
 
+/// The file name that stands for standard input.
+pub const STDIN: &str = "-";
+
+fn read_input(filename: &str) -> io::Result<String> {
+    if filename == STDIN {
+        let mut contents = String::new();
+        io::stdin().read_to_string(&mut contents)?;
+        Ok(contents)
+    } else {
+        fs::read_to_string(filename)
+    }
+}
+
+/// Command line interface, parsed by clap.
+#[derive(Parser, Debug)]
+#[command(version, about = "Search for a pattern in files")]
+struct Cli {
+    /// Text to search for
+    #[arg(value_name = "PATTERN")]
+    query: String,
+
+    /// Files to search in, `-` reads standard input
+    #[arg(value_name = "FILE", required = true)]
+    filenames: Vec<String>,
+
+    /// Match regardless of case (also enabled by the CASE_INSENSITIVE env var)
+    #[arg(short, long)]
+    ignore_case: bool,
+
+    /// Print only the number of matching lines
+    #[arg(short, long)]
+    count: bool,
+
+    /// Prefix each match with its line number
+    #[arg(short = 'n', long)]
+    line_number: bool,
+}
+
+/// The resolved search settings.
+#[derive(Debug, PartialEq)]
 pub struct Config {
     pub query: String,
-    pub filename: String,
+    pub filenames: Vec<String>,
     pub case_sensitive: bool,
+    pub count: bool,
+    pub line_number: bool,
 }
 
 impl Config {
-    pub fn new(args: &[String]) -> Result<Config, &str> {
-        if args.len() < 3 {
-            return Err("Not Enough Arguments");
-        }
-
-        let query = args[1].clone();
-        let filename = args[2].clone();
+    /// Parses the full argument vector, program name included.
+    ///
+    /// `--help` and `--version` come back as a `clap::Error` too; calling
+    /// `exit()` on it prints the text and exits with the right status.
+    pub fn new(args: &[String]) -> Result<Config, clap::Error> {
+        let cli = Cli::try_parse_from(args)?;
 
-        let case_sensitive = env::var("CASE_INSENSITIVE").is_err();
+        let case_sensitive = !cli.ignore_case && env::var("CASE_INSENSITIVE").is_err();
         Ok(Config {
-            query,
-            filename,
+            query: cli.query,
+            filenames: cli.filenames,
             case_sensitive,
+            count: cli.count,
+            line_number: cli.line_number,
         })
     }
+
+    /// Like [`Config::new`], but also checks that every file exists and can be
+    /// read, so a typo is reported before any searching starts.
+    ///
+    /// `-` (standard input) is not checked.
+    pub fn build(args: &[String]) -> Result<Config, ConfigError> {
+        let config = Config::new(args).map_err(ConfigError::Args)?;
+        for filename in config.filenames.iter().filter(|name| *name != STDIN) {
+            check_readable(filename)?;
+        }
+        Ok(config)
+    }
 }
 
-pub fn search<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
-    let mut result = Vec::new();
-    for line in contents.lines() {
-        if line.contains(query) {
-            result.push(line);
+fn check_readable(filename: &str) -> Result<(), ConfigError> {
+    let error = |reason: &str| ConfigError::File(format!("file '{}' {}", filename, reason));
+    match fs::File::open(filename) {
+        Ok(file) => match file.metadata() {
+            Ok(metadata) if metadata.is_dir() => Err(error("is a directory")),
+            _ => Ok(()),
+        },
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Err(error("not found")),
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+            Err(error("is not readable: permission denied"))
         }
+        Err(e) => Err(error(&format!("could not be opened: {}", e))),
     }
-    result
 }
 
-pub fn search_case_senstive<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
-    let query = query.to_lowercase();
-    let mut results = Vec::new();
+/// Why [`Config::build`] failed.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The arguments could not be parsed (this includes `--help` and `--version`).
+    Args(clap::Error),
+    /// A file is missing or unreadable.
+    File(String),
+}
 
-    for line in contents.lines() {
-        if line.to_lowercase().contains(&query) {
-            results.push(line);
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Args(e) => write!(f, "{}", e),
+            ConfigError::File(message) => write!(f, "{}", message),
         }
     }
-    results
+}
+
+impl Error for ConfigError {}
+
+pub fn search<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
+    search_iter(query, contents).collect()
+}
+
+pub fn search_case_senstive<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
+    search_case_insensitive_iter(query, contents).collect()
+}
+
+/// Lazily yields the lines of `contents` that contain `query`.
+///
+/// Nothing is allocated up front, so callers can `take`, `count`
+/// or stop early without scanning the whole input.
+pub fn search_iter<'a>(query: &str, contents: &'a str) -> impl Iterator<Item = &'a str> {
+    search_numbered_iter(query, contents).map(|(_, line)| line)
+}
+
+/// Case-insensitive counterpart of [`search_iter`].
+pub fn search_case_insensitive_iter<'a>(
+    query: &str,
+    contents: &'a str,
+) -> impl Iterator<Item = &'a str> {
+    search_case_insensitive_numbered_iter(query, contents).map(|(_, line)| line)
+}
+
+/// Like [`search_iter`], but each line comes with its line number, counting from 1.
+pub fn search_numbered_iter<'a>(
+    query: &str,
+    contents: &'a str,
+) -> impl Iterator<Item = (usize, &'a str)> {
+    numbered_lines(contents).filter(move |(_, line)| line.contains(query))
+}
+
+/// Case-insensitive counterpart of [`search_numbered_iter`].
+pub fn search_case_insensitive_numbered_iter<'a>(
+    query: &str,
+    contents: &'a str,
+) -> impl Iterator<Item = (usize, &'a str)> {
+    let query = query.to_lowercase();
+    numbered_lines(contents).filter(move |(_, line)| line.to_lowercase().contains(&query))
+}
+
+fn numbered_lines(contents: &str) -> impl Iterator<Item = (usize, &str)> {
+    contents
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line))
 }
 
 #[cfg(test)]
@@ -70,10 +208,9 @@ mod tests {
     fn one_result() {
         let query = "duct";
         let contents = "\
-        Rust:
-        Safe,fast,productive
-        Pick three.
-        ";
+Rust:
+safe,fast,productive
+Pick three.";
 
         assert_eq!(vec!["safe,fast,productive"], search(query, contents));
     }
@@ -82,10 +219,114 @@ mod tests {
     fn case_insenstive() {
         let query = "rUst";
         let contents = "\
-        Rust:
-        safe,fast,productive
-        Pick three.";
+Rust:
+safe,fast,productive
+Pick three.";
 
         assert_eq!(vec!["Rust:"], search_case_senstive(query, contents));
     }
+
+    #[test]
+    fn iter_matches_vec_version() {
+        let contents = "\
+Rust:
+safe, fast, productive.
+Trust me.
+Pick three.";
+
+        let from_iter: Vec<&str> = search_iter("ust", contents).collect();
+        assert_eq!(search("ust", contents), from_iter);
+
+        let from_iter: Vec<&str> = search_case_insensitive_iter("RUST", contents).collect();
+        assert_eq!(search_case_senstive("RUST", contents), from_iter);
+    }
+
+    #[test]
+    fn iter_supports_early_termination() {
+        let contents = "one match\ntwo match\nthree match";
+
+        let mut matches = search_iter("match", contents);
+        assert_eq!(Some("one match"), matches.next());
+
+        assert_eq!(
+            vec!["one match", "two match"],
+            search_iter("match", contents).take(2).collect::<Vec<_>>()
+        );
+        assert_eq!(3, search_iter("match", contents).count());
+    }
+
+    #[test]
+    fn numbered_iter_counts_lines_from_one() {
+        let contents = "\
+Rust:
+safe, fast, productive.
+Trust me.";
+
+        assert_eq!(
+            vec![(1, "Rust:"), (3, "Trust me.")],
+            search_numbered_iter("ust", contents).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![(1, "Rust:"), (3, "Trust me.")],
+            search_case_insensitive_numbered_iter("RUST", contents).collect::<Vec<_>>()
+        );
+    }
+
+    fn args(list: &[&str]) -> Vec<String> {
+        std::iter::once("minigrep")
+            .chain(list.iter().copied())
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn config_from_positional_args() {
+        let config = Config::new(&args(&["to", "poem.txt"])).unwrap();
+
+        assert_eq!(config.query, "to");
+        assert_eq!(config.filenames, vec!["poem.txt"]);
+        assert!(!config.count);
+        assert!(!config.line_number);
+    }
+
+    #[test]
+    fn config_flags_in_any_order() {
+        let before = Config::new(&args(&["-i", "--count", "to", "a.txt", "b.txt"])).unwrap();
+        let after =
+            Config::new(&args(&["to", "a.txt", "b.txt", "--count", "--ignore-case"])).unwrap();
+        let mixed = Config::new(&args(&["--ignore-case", "to", "-c", "a.txt", "b.txt"])).unwrap();
+
+        assert_eq!(before, after);
+        assert_eq!(before, mixed);
+        assert_eq!(before.filenames, vec!["a.txt", "b.txt"]);
+        assert!(!before.case_sensitive);
+        assert!(before.count);
+
+        let numbered = Config::new(&args(&["-n", "to", "poem.txt"])).unwrap();
+        assert!(numbered.line_number);
+    }
+
+    #[test]
+    fn config_rejects_missing_file() {
+        let err = Config::new(&args(&["to"])).unwrap_err();
+        assert_eq!(err.kind(), clap::error::ErrorKind::MissingRequiredArgument);
+
+        let err = Config::new(&args(&["to", "poem.txt", "--bogus"])).unwrap_err();
+        assert_eq!(err.kind(), clap::error::ErrorKind::UnknownArgument);
+    }
+
+    #[test]
+    fn build_reports_missing_file() {
+        let err = Config::build(&args(&["to", "no-such-file.txt"])).unwrap_err();
+        assert_eq!(err.to_string(), "file 'no-such-file.txt' not found");
+    }
+
+    #[test]
+    fn build_accepts_existing_file_and_stdin() {
+        let config = Config::build(&args(&["to", "Cargo.toml", STDIN])).unwrap();
+        assert_eq!(config.filenames, vec!["Cargo.toml", "-"]);
+
+        let err = Config::build(&args(&["to", "src"])).unwrap_err();
+        assert_eq!(err.to_string(), "file 'src' is a directory");
+    }
 }
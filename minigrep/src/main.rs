@@ -1,4 +1,4 @@
-use minigrep::Config;
+use minigrep::{Config, ConfigError};
 use std::env;
 use std::process;
 
@@ -6,13 +6,16 @@ fn main() {
     let args: Vec<String> = env::args().collect();
     println!("{:?}", args);
 
-    let config = Config::new(&args).unwrap_or_else(|err| {
-        println!("Problem Parsing Arguments : {} ", err);
-        process::exit(1);
+    let config = Config::build(&args).unwrap_or_else(|err| match err {
+        ConfigError::Args(err) => err.exit(),
+        ConfigError::File(message) => {
+            println!("Problem with the input: {}", message);
+            process::exit(1);
+        }
     });
 
     println!("Searching for {} ", config.query);
-    print!("In File {} ", config.filename);
+    println!("In File {} ", config.filenames.join(", "));
 
     if let Err(e) = minigrep::run(config) {
         println!("Application Error : {}", e);